@@ -1,107 +1,718 @@
+use crate::bench::{BenchScene, Benchmark};
 use crate::camera::Camera;
+use crate::console::{self, Command, Console};
 use crate::egui_tools::EguiRenderer;
+use crate::entities::{Entities, Renderable, Transform, Velocity};
+use crate::entity_sprites::EntitySprites;
+use crate::game_state::{self, GameState, MenuChoice};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::headless;
+use crate::hotkeys::{Action, InputContext};
+use crate::logging::LogViewer;
+use crate::navigation::Navigation;
+use crate::particles::ParticleSystem;
+use crate::perf::{FrameLimiter, FrameStats, GpuMemory, GpuTimer};
+use crate::plugin::EnginePlugin;
+use crate::profiler::Profiler;
+use crate::recording::{InputFrame, InputPlayback, InputRecorder};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::remote::RemoteControl;
+use crate::render_scale::{self, ScaledTarget};
+use crate::scene;
+use crate::scene_file::{CameraPose, EntityDescription, SceneFile, SCENE_DIR};
+use crate::settings::{PresentMode, QualityPreset, Settings, ThemePreset, ThemeSettings};
+#[cfg(not(target_arch = "wasm32"))]
+use crate::tool_window::ToolWindow;
+use crate::touch::TouchControls;
 use egui_wgpu::{wgpu, ScreenDescriptor};
+use std::path::{Component, Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
+use web_time::Instant;
 use winit::application::ApplicationHandler;
-use winit::dpi::PhysicalSize;
-use winit::event::{DeviceEvent, ElementState, MouseButton, WindowEvent};
-use winit::event_loop::ActiveEventLoop;
-use winit::window::{Window, WindowId};
+use winit::dpi::{PhysicalPosition, PhysicalSize};
+use winit::event::{DeviceEvent, ElementState, MouseButton, StartCause, TouchPhase, WindowEvent};
+use winit::event_loop::{ActiveEventLoop, ControlFlow, EventLoop};
+use winit::window::{CursorGrabMode, Window, WindowId};
 
+const RECORDING_PATH: &str = "input_recording.jsonl";
+/// Where screenshots and photos are saved.
+#[cfg(not(target_arch = "wasm32"))]
+const SCREENSHOT_DIR: &str = "screenshots";
+
+/// Length of one simulation tick.
+pub const FIXED_TIMESTEP: Duration = Duration::from_nanos(1_000_000_000 / 60);
+/// Upper bound on ticks run in a single frame, so a long stall doesn't snowball.
+const MAX_TICKS_PER_FRAME: u32 = 8;
+/// Longest wait before retrying after repeated surface timeouts.
+const MAX_SURFACE_BACKOFF: Duration = Duration::from_secs(1);
+/// How long settings must stay unchanged before they are written to disk.
+const SETTINGS_SAVE_DELAY: Duration = Duration::from_secs(1);
+/// Longest the event loop sleeps before checking for remote commands, which
+/// don't wake it up by themselves.
+#[cfg(not(target_arch = "wasm32"))]
+const REMOTE_POLL_INTERVAL: Duration = Duration::from_millis(50);
+
+/// Everything needed to draw into one window: the device, the surface, egui and
+/// the camera. [`App`] drives it from winit events, but it can also be embedded
+/// in another event loop by calling [`AppState::update`] and [`AppState::render`].
 pub struct AppState {
+    adapter: wgpu::Adapter,
+    adapter_info: wgpu::AdapterInfo,
+    /// Adapters that can present to the window's surface.
+    adapters: Vec<wgpu::Adapter>,
+    /// Adapter to switch to at the start of the next frame.
+    pending_adapter: Option<usize>,
+    /// Surface size to apply at the start of the next frame, so a burst of
+    /// resize events reconfigures the surface only once.
+    pending_size: Option<(u32, u32)>,
+    /// Present modes the surface supports on the current adapter.
+    present_modes: Vec<wgpu::PresentMode>,
+    /// FPS and latency last measured in each present mode, for comparison.
+    present_mode_results: Vec<(PresentMode, f32, f32)>,
     pub device: wgpu::Device,
     pub queue: wgpu::Queue,
     pub surface_config: wgpu::SurfaceConfiguration,
     pub surface: wgpu::Surface<'static>,
-    pub scale_factor: f32,
     pub egui_renderer: EguiRenderer,
     pub window: Arc<Window>,
     camera: Camera,
+    /// The camera as of the previous tick, for drawing between ticks.
+    previous_camera: Camera,
+    /// How far rendering is between the previous and the current tick.
+    interpolation: f32,
+    entities: Entities,
+    entity_sprites: EntitySprites,
+    particles: ParticleSystem,
+    /// Where the scene is drawn while the render scale isn't 1.
+    scaled_target: Option<ScaledTarget>,
     pressed_keys: Vec<winit::keyboard::KeyCode>,
+    modifiers: winit::keyboard::ModifiersState,
     mouse_delta: (f32, f32),
     cursor_locked: bool,
+    /// Where the last `CursorMoved` left the cursor while it's locked.
+    last_cursor_position: Option<PhysicalPosition<f64>>,
+    /// Set once the platform refuses to move the cursor (Wayland, the web); mouse
+    /// look then uses raw motion.
+    cursor_warp_unsupported: bool,
+    ui_mode: bool,
+    /// Hides every window except the main menu, e.g. for clean screenshots.
+    ui_hidden: bool,
+    /// Freezes the simulation and hides the UI while the camera keeps flying.
+    photo_mode: bool,
+    /// Theme last applied to egui, to reapply it only after a change.
+    applied_theme: Option<ThemeSettings>,
+    game_state: GameState,
+    /// Scene files offered by the main menu.
+    scene_files: Vec<PathBuf>,
+    /// Shows the controls window on top of the main menu.
+    show_settings: bool,
+    /// Set from the menus; the app exits after the current frame.
+    exit_requested: bool,
+    console: Console,
+    log_viewer: LogViewer,
+    profiler: Profiler,
+    navigation: Navigation,
+    /// The profiler is shown in a window of its own, owned by [`App`].
+    profiler_detached: bool,
+    /// Where to save a screenshot of the next frame.
+    #[cfg(not(target_arch = "wasm32"))]
+    screenshot_path: Option<PathBuf>,
+    touch_controls: TouchControls,
+    last_tap: Option<glam::Vec2>,
+    recorder: Option<InputRecorder>,
+    playback: Option<InputPlayback>,
+    frame_stats: FrameStats,
+    gpu_timer: Option<GpuTimer>,
+    gpu_memory: GpuMemory,
+    /// Time the last frame spent blocked on acquiring the surface texture.
+    surface_wait: Duration,
+    /// How long egui said it could wait before the next repaint.
+    egui_repaint_delay: Duration,
+    settings: Settings,
+    saved_settings: Settings,
+    settings_changed_at: Option<Instant>,
+    plugins: Vec<Box<dyn EnginePlugin>>,
 }
 
 impl AppState {
-    async fn new(
+    /// Creates the surface and device for `window`, sized from the saved settings.
+    pub async fn for_window(instance: wgpu::Instance, window: Arc<Window>) -> Self {
+        let settings = Settings::load();
+
+        let _ = window.request_inner_size(PhysicalSize::new(
+            settings.window_width,
+            settings.window_height,
+        ));
+
+        let surface = instance
+            .create_surface(window.clone())
+            .expect("Failed to create surface!");
+
+        Self::new(&instance, surface, window, settings).await
+    }
+
+    /// Creates the device for an existing surface of `window`, preferring the
+    /// adapter named in `settings`.
+    pub async fn new(
         instance: &wgpu::Instance,
         surface: wgpu::Surface<'static>,
         window: Arc<Window>,
-        width: u32,
-        height: u32,
+        mut settings: Settings,
     ) -> Self {
-        let adapter = instance
-            .request_adapter(&wgpu::RequestAdapterOptions {
-                power_preference: wgpu::PowerPreference::default(),
-                force_fallback_adapter: false,
-                compatible_surface: Some(&surface),
-            })
-            .await
-            .expect("Failed to find an appropriate adapter");
-
-        let (device, queue) = adapter
-            .request_device(
-                &wgpu::DeviceDescriptor {
-                    label: None,
-                    required_features: wgpu::Features::empty(),
-                    required_limits: wgpu::Limits::default(),
-                    memory_hints: wgpu::MemoryHints::default(),
-                },
-                None,
-            )
-            .await
-            .expect("Failed to create device");
-
-        let swapchain_capabilities = surface.get_capabilities(&adapter);
-        let selected_format = wgpu::TextureFormat::Bgra8UnormSrgb;
-        let swapchain_format = swapchain_capabilities
-            .formats
-            .iter()
-            .find(|d| **d == selected_format)
-            .expect("failed to select proper surface texture format!");
-
-        let surface_config = wgpu::SurfaceConfiguration {
-            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
-            format: *swapchain_format,
-            width,
-            height,
-            present_mode: wgpu::PresentMode::AutoVsync,
-            desired_maximum_frame_latency: 2,
-            alpha_mode: swapchain_capabilities.alpha_modes[0],
-            view_formats: vec![],
+        // Browsers only hand out adapters through `request_adapter`.
+        #[cfg(not(target_arch = "wasm32"))]
+        let adapters: Vec<wgpu::Adapter> = instance
+            .enumerate_adapters(wgpu::Backends::all())
+            .into_iter()
+            .filter(|adapter| adapter.is_surface_supported(&surface))
+            .collect();
+        #[cfg(target_arch = "wasm32")]
+        let adapters: Vec<wgpu::Adapter> = Vec::new();
+
+        let preferred = settings
+            .adapter
+            .as_ref()
+            .and_then(|name| adapters.iter().find(|a| a.get_info().name == *name));
+        let adapter = match preferred {
+            Some(adapter) => adapter.clone(),
+            None => instance
+                .request_adapter(&wgpu::RequestAdapterOptions {
+                    power_preference: wgpu::PowerPreference::default(),
+                    force_fallback_adapter: false,
+                    compatible_surface: Some(&surface),
+                })
+                .await
+                .expect("Failed to find an appropriate adapter"),
         };
 
+        // Differs from `settings` after detecting a preset, so it gets saved.
+        let saved_settings = settings.clone();
+        if settings.quality.is_none() {
+            let info = adapter.get_info();
+            let preset = QualityPreset::detect(&info);
+            tracing::info!("Picked {preset:?} quality for {}", info.name);
+            settings.apply_quality(preset);
+        }
+
+        let (device, queue) = create_device(&adapter).await;
+        let surface_config = create_surface_config(
+            &adapter,
+            &surface,
+            settings.window_width,
+            settings.window_height,
+            settings.present_mode,
+        );
         surface.configure(&device, &surface_config);
 
         let egui_renderer = EguiRenderer::new(&device, surface_config.format, None, 1, &window);
-        let camera = Camera::new();
+        let camera = Camera::new(&settings.camera);
+        let gpu_timer = GpuTimer::new(&device, &queue);
+        let entity_sprites = EntitySprites::new(&device, surface_config.format);
+        let particles = ParticleSystem::new(&device, surface_config.format);
 
         Self {
+            adapter_info: adapter.get_info(),
+            adapters,
+            pending_adapter: None,
+            pending_size: None,
+            present_modes: surface.get_capabilities(&adapter).present_modes,
+            adapter,
+            present_mode_results: Vec::new(),
             device,
             queue,
             surface,
             surface_config,
             egui_renderer,
-            scale_factor: 1.0,
             window,
+            previous_camera: camera.clone(),
             camera,
+            interpolation: 1.0,
+            entities: Entities::new(),
+            entity_sprites,
+            particles,
+            scaled_target: None,
             pressed_keys: Vec::new(),
+            modifiers: winit::keyboard::ModifiersState::empty(),
             mouse_delta: (0.0, 0.0),
             cursor_locked: false,
+            last_cursor_position: None,
+            cursor_warp_unsupported: false,
+            ui_mode: false,
+            ui_hidden: false,
+            photo_mode: false,
+            applied_theme: None,
+            game_state: GameState::MainMenu,
+            scene_files: SceneFile::list(),
+            show_settings: false,
+            exit_requested: false,
+            console: Console::new(),
+            log_viewer: LogViewer::new(),
+            profiler: Profiler::new(),
+            navigation: Navigation::new(),
+            profiler_detached: false,
+            #[cfg(not(target_arch = "wasm32"))]
+            screenshot_path: None,
+            touch_controls: TouchControls::new(),
+            last_tap: None,
+            recorder: None,
+            playback: None,
+            frame_stats: FrameStats::new(),
+            gpu_timer,
+            gpu_memory: GpuMemory::new(),
+            surface_wait: Duration::ZERO,
+            egui_repaint_delay: Duration::ZERO,
+            saved_settings,
+            settings,
+            plugins: Vec::new(),
+            settings_changed_at: None,
+        }
+    }
+
+    /// Recreates the device and everything that depends on it on another adapter.
+    fn switch_adapter(&mut self, adapter: wgpu::Adapter) {
+        let (device, queue) = pollster::block_on(create_device(&adapter));
+        self.surface_config = create_surface_config(
+            &adapter,
+            &self.surface,
+            self.surface_config.width,
+            self.surface_config.height,
+            self.settings.present_mode,
+        );
+        self.surface.configure(&device, &self.surface_config);
+
+        self.egui_renderer =
+            EguiRenderer::new(&device, self.surface_config.format, None, 1, &self.window);
+        self.applied_theme = None;
+        self.gpu_timer = GpuTimer::new(&device, &queue);
+        let particle_settings = self.particles.settings();
+        let particles_frozen = self.particles.frozen;
+        self.entity_sprites = EntitySprites::new(&device, self.surface_config.format);
+        self.particles = ParticleSystem::new(&device, self.surface_config.format);
+        self.particles.apply_settings(&particle_settings);
+        self.particles.frozen = particles_frozen;
+        self.scaled_target = None;
+        self.device = device;
+        self.queue = queue;
+
+        self.present_modes = self.surface.get_capabilities(&adapter).present_modes;
+        self.adapter_info = adapter.get_info();
+        self.adapter = adapter;
+        self.settings.adapter = Some(self.adapter_info.name.clone());
+
+        for plugin in &mut self.plugins {
+            plugin.setup(&self.device, &self.queue, self.surface_config.format);
         }
     }
 
-    fn resize_surface(&mut self, width: u32, height: u32) {
+    pub fn add_plugin(&mut self, mut plugin: Box<dyn EnginePlugin>) {
+        plugin.setup(&self.device, &self.queue, self.surface_config.format);
+        self.plugins.push(plugin);
+    }
+
+    pub fn camera(&self) -> &Camera {
+        &self.camera
+    }
+
+    pub fn camera_mut(&mut self) -> &mut Camera {
+        &mut self.camera
+    }
+
+    /// Moves the camera to `camera` without interpolating from the last tick's
+    /// pose, for teleports and resets.
+    pub fn set_camera_discontinuous(&mut self, camera: Camera) {
+        self.previous_camera = camera.clone();
+        self.camera = camera;
+    }
+
+    pub fn entities(&self) -> &Entities {
+        &self.entities
+    }
+
+    pub fn entities_mut(&mut self) -> &mut Entities {
+        &mut self.entities
+    }
+
+    pub fn settings(&self) -> &Settings {
+        &self.settings
+    }
+
+    /// Resizes the surface at the start of the next [`AppState::render`].
+    pub fn resize_surface(&mut self, width: u32, height: u32) {
+        self.pending_size = Some((width, height));
+    }
+
+    fn configure_surface(&mut self, width: u32, height: u32) {
         self.surface_config.width = width;
         self.surface_config.height = height;
+        self.settings.window_width = width;
+        self.settings.window_height = height;
         self.surface.configure(&self.device, &self.surface_config);
     }
 
-    fn render(&mut self) {
-        let surface_texture = match self.surface.get_current_texture() {
+    /// Writes the settings once they have stopped changing for a moment, or right away if `force`.
+    fn save_settings(&mut self, force: bool) {
+        if self.settings == self.saved_settings {
+            self.settings_changed_at = None;
+            return;
+        }
+        let changed_at = *self.settings_changed_at.get_or_insert_with(Instant::now);
+        if !force && changed_at.elapsed() < SETTINGS_SAVE_DELAY {
+            return;
+        }
+
+        if let Err(err) = self.settings.save() {
+            tracing::error!("Failed to save settings: {err}");
+        }
+        self.saved_settings = self.settings.clone();
+        self.settings_changed_at = None;
+    }
+
+    fn set_cursor_locked(&mut self, locked: bool) {
+        if self.cursor_locked == locked {
+            return;
+        }
+        self.cursor_locked = locked;
+        self.last_cursor_position = None;
+
+        // Accelerated motion is read from cursor movement, which a locked cursor doesn't produce.
+        let (preferred, fallback) = if self.uses_raw_motion() {
+            (CursorGrabMode::Locked, CursorGrabMode::Confined)
+        } else {
+            (CursorGrabMode::Confined, CursorGrabMode::Locked)
+        };
+        let result = if locked {
+            self.window
+                .set_cursor_grab(preferred)
+                .or_else(|_| self.window.set_cursor_grab(fallback))
+        } else {
+            self.window.set_cursor_grab(CursorGrabMode::None)
+        };
+        if let Err(err) = result {
+            tracing::warn!("Failed to change the cursor grab: {err}");
+        }
+        self.window.set_cursor_visible(!locked);
+    }
+
+    /// Whether mouse look reads `DeviceEvent::MouseMotion` instead of cursor movement.
+    fn uses_raw_motion(&self) -> bool {
+        self.settings.mouse.raw_motion || self.cursor_warp_unsupported
+    }
+
+    /// In UI mode all gameplay input is suspended so egui gets the keyboard and mouse.
+    fn set_ui_mode(&mut self, ui_mode: bool) {
+        self.ui_mode = ui_mode;
+        if ui_mode {
+            self.suspend_gameplay_input();
+        }
+    }
+
+    /// Menus and the pause overlay release the cursor and stop the simulation.
+    fn set_game_state(&mut self, game_state: GameState) {
+        self.game_state = game_state;
+        if game_state != GameState::Playing {
+            self.set_photo_mode(false);
+        }
+        if game_state == GameState::MainMenu {
+            self.scene_files = SceneFile::list();
+        }
+        if game_state != GameState::Playing {
+            self.suspend_gameplay_input();
+        }
+    }
+
+    /// The camera pose, particle settings and entities as a scene file.
+    pub fn capture_scene(&mut self) -> SceneFile {
+        let mut query =
+            self.entities
+                .world_mut()
+                .query::<(&Transform, Option<&Velocity>, Option<&Renderable>)>();
+        let entities = query
+            .iter(self.entities.world())
+            .map(|(transform, velocity, renderable)| EntityDescription {
+                transform: *transform,
+                velocity: velocity.copied(),
+                renderable: renderable.copied(),
+            })
+            .collect();
+        let position = self.camera.get_position();
+        SceneFile {
+            camera: CameraPose {
+                position,
+                target: position + self.camera.get_direction(),
+            },
+            particles: self.particles.settings(),
+            entities,
+            waypoints: self.navigation.waypoints.clone(),
+        }
+    }
+
+    /// Replaces the camera pose, particle settings and entities with those of `scene`.
+    pub fn apply_scene(&mut self, scene: &SceneFile) {
+        let mut camera = self.camera.clone();
+        camera.set_position(scene.camera.position);
+        camera.look_at(scene.camera.target);
+        self.set_camera_discontinuous(camera);
+        self.particles.apply_settings(&scene.particles);
+
+        self.navigation.waypoints = scene.waypoints.clone();
+
+        self.entities.clear();
+        for entity in &scene.entities {
+            self.entities.spawn_description(entity);
+        }
+    }
+
+    fn set_photo_mode(&mut self, photo_mode: bool) {
+        if photo_mode == self.photo_mode {
+            return;
+        }
+        self.photo_mode = photo_mode;
+        self.particles.frozen = photo_mode;
+        if photo_mode {
+            tracing::info!("Photo mode: F12 saves a photo, F2 leaves");
+        }
+    }
+
+    fn suspend_gameplay_input(&mut self) {
+        self.set_cursor_locked(false);
+        self.pressed_keys.clear();
+        self.mouse_delta = (0.0, 0.0);
+    }
+
+    fn input_context(&self) -> InputContext {
+        if self.ui_mode || self.game_state != GameState::Playing {
+            InputContext::Menu
+        } else if self.cursor_locked {
+            InputContext::Fly
+        } else {
+            InputContext::Viewport
+        }
+    }
+
+    fn run_action(&mut self, action: Action) {
+        match action {
+            Action::ReleaseCursor => self.set_cursor_locked(false),
+            Action::ToggleUiMode => self.set_ui_mode(!self.ui_mode),
+            Action::ToggleUiVisible => self.ui_hidden = !self.ui_hidden,
+            // The console isn't drawn while the UI is hidden.
+            Action::ToggleConsole if !self.shows_windows() => {}
+            Action::ToggleConsole => {
+                self.console.toggle();
+                if self.console.open {
+                    self.suspend_gameplay_input();
+                }
+            }
+            Action::TogglePause => match self.game_state {
+                GameState::Playing => self.set_game_state(GameState::Paused),
+                GameState::Paused => self.set_game_state(GameState::Playing),
+                GameState::MainMenu => {}
+            },
+            Action::ResetCamera => {
+                self.set_camera_discontinuous(Camera::new(&self.settings.camera))
+            }
+            Action::TogglePhotoMode => {
+                if self.game_state == GameState::Playing {
+                    self.set_photo_mode(!self.photo_mode);
+                }
+            }
+            Action::SavePhoto => {
+                let seconds = web_time::SystemTime::now()
+                    .duration_since(web_time::UNIX_EPOCH)
+                    .unwrap_or_default()
+                    .as_secs();
+                let path = format!("photo_{seconds}.png");
+                if let Err(err) = take_screenshot(self, &[&path]) {
+                    self.console.print(&format!("Error: {err}"));
+                }
+            }
+        }
+    }
+
+    /// Whether anything moves the camera or the scene, so low power mode must keep rendering.
+    fn is_active(&self) -> bool {
+        !self.pressed_keys.is_empty()
+            || self.mouse_delta != (0.0, 0.0)
+            || self.touch_controls.is_active()
+            || self.recorder.is_some()
+            || self.playback.is_some()
+            || self.simulation_moves()
+    }
+
+    /// Whether the next tick moves particles or entities.
+    fn simulation_moves(&self) -> bool {
+        self.game_state == GameState::Playing
+            && !self.photo_mode
+            && (self.particles.is_animating() || self.entities.has_motion())
+    }
+
+    /// Whether the UI windows are drawn. The main menu is shown even with the UI hidden.
+    fn shows_windows(&self) -> bool {
+        (!self.ui_hidden && !self.photo_mode) || self.game_state == GameState::MainMenu
+    }
+
+    fn wants_keyboard_input(&self) -> bool {
+        self.ui_mode
+            || (self.console.open && self.shows_windows())
+            || self.game_state != GameState::Playing
+            || self.egui_renderer.context().wants_keyboard_input()
+    }
+
+    fn wants_pointer_input(&self) -> bool {
+        self.ui_mode
+            || self.game_state != GameState::Playing
+            || self.egui_renderer.context().wants_pointer_input()
+    }
+
+    /// Drains the live input accumulated since the last tick.
+    fn take_input(&mut self) -> InputFrame {
+        let mut mouse_delta = (0.0, 0.0);
+        if self.cursor_locked {
+            mouse_delta = std::mem::take(&mut self.mouse_delta);
+            if self.settings.mouse.invert_y {
+                mouse_delta.1 = -mouse_delta.1;
+            }
+        }
+
+        if let Some(tap) = self.touch_controls.take_tap() {
+            self.last_tap = Some(tap);
+        }
+
+        InputFrame {
+            time: 0.0,
+            keys: self.pressed_keys.clone(),
+            mouse_delta,
+            touch_movement: self.touch_controls.movement().into(),
+            touch_look: self.touch_controls.take_look_delta().into(),
+        }
+    }
+
+    fn apply_input(&mut self, input: &InputFrame) {
+        self.camera.handle_input(&input.keys);
+
+        if input.mouse_delta != (0.0, 0.0) {
+            self.camera
+                .handle_mouse(&(input.mouse_delta.0 as f64, input.mouse_delta.1 as f64));
+        }
+
+        self.camera.handle_analog(input.touch_movement.into());
+        if input.touch_look != (0.0, 0.0) {
+            self.camera
+                .handle_mouse(&(input.touch_look.0 as f64, input.touch_look.1 as f64));
+        }
+    }
+
+    /// Advances the simulation by one fixed tick, replaying or recording input if requested.
+    pub fn update(&mut self) {
+        puffin::profile_function!();
+        self.previous_camera = self.camera.clone();
+        let mut input = self.take_input();
+        if self.game_state != GameState::Playing {
+            return;
+        }
+
+        if let Some(playback) = self.playback.as_mut() {
+            match playback.next_frame() {
+                Some(recorded) => input = recorded,
+                None => self.playback = None,
+            }
+        }
+        if let Some(recorder) = self.recorder.as_mut() {
+            recorder.record(&input);
+        }
+
+        self.apply_input(&input);
+        if self.photo_mode {
+            return;
+        }
+        {
+            puffin::profile_scope!("entities");
+            self.entities.update(FIXED_TIMESTEP.as_secs_f32());
+        }
+        {
+            puffin::profile_scope!("particles");
+            self.particles
+                .step(&self.device, &self.queue, FIXED_TIMESTEP.as_secs_f32());
+        }
+
+        for plugin in &mut self.plugins {
+            plugin.update(FIXED_TIMESTEP.as_secs_f32(), &mut self.camera);
+        }
+    }
+
+    /// Sets the fraction of a tick that has passed since the last
+    /// [`AppState::update`]. Rendering draws the camera that far between its
+    /// previous and current tick, so motion stays smooth at any frame rate.
+    pub fn set_interpolation(&mut self, alpha: f32) {
+        self.interpolation = alpha.clamp(0.0, 1.0);
+    }
+
+    /// Reconfigures the surface at the window's current size, e.g. after it was lost or outdated.
+    fn reconfigure_surface(&mut self) {
+        let size = self.window.inner_size();
+        if size.width > 0 && size.height > 0 {
+            self.configure_surface(size.width, size.height);
+        }
+    }
+
+    #[cfg(not(target_arch = "wasm32"))]
+    fn save_screenshot(&mut self, texture: &wgpu::Texture, path: &Path) {
+        let readable_format = matches!(
+            texture.format(),
+            wgpu::TextureFormat::Rgba8Unorm
+                | wgpu::TextureFormat::Rgba8UnormSrgb
+                | wgpu::TextureFormat::Bgra8Unorm
+                | wgpu::TextureFormat::Bgra8UnormSrgb
+        );
+        if !readable_format || !texture.usage().contains(wgpu::TextureUsages::COPY_SRC) {
+            self.console
+                .print("Error: this surface doesn't support screenshots");
+            return;
+        }
+        let encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("screenshot encoder"),
+            });
+        let result = headless::read_texture(&self.device, &self.queue, encoder, texture)
+            .map_err(std::io::Error::other)
+            .and_then(|image| {
+                if let Some(parent) = path.parent() {
+                    std::fs::create_dir_all(parent)?;
+                }
+                image.save_png(path)
+            });
+        match result {
+            Ok(()) => self
+                .console
+                .print(&format!("Saved screenshot to {}", path.display())),
+            Err(err) => self
+                .console
+                .print(&format!("Error: failed to save screenshot: {err}")),
+        }
+    }
+
+    /// Renders a frame. Lost and outdated surfaces are recovered here by skipping
+    /// the frame; other surface errors are left to the caller.
+    pub fn render(&mut self) -> Result<(), wgpu::SurfaceError> {
+        puffin::profile_function!();
+        if let Some((width, height)) = self.pending_size.take() {
+            self.configure_surface(width, height);
+        }
+        let acquire_start = Instant::now();
+        let acquired = {
+            puffin::profile_scope!("acquire surface");
+            self.surface.get_current_texture()
+        };
+        let surface_texture = match acquired {
             Ok(texture) => texture,
-            Err(_) => return,
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.reconfigure_surface();
+                return Ok(());
+            }
+            Err(err) => return Err(err),
         };
+        self.surface_wait = acquire_start.elapsed();
         let view = surface_texture
             .texture
             .create_view(&wgpu::TextureViewDescriptor::default());
@@ -110,63 +721,382 @@ impl AppState {
             .device
             .create_command_encoder(&wgpu::CommandEncoderDescriptor { label: None });
 
-        // Clear the screen
+        if let Some(timer) = self.gpu_timer.as_mut() {
+            timer.begin(&mut encoder);
+        }
+
+        let aspect = self.surface_config.width as f32 / self.surface_config.height as f32;
         {
-            let _rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
-                label: None,
-                color_attachments: &[Some(wgpu::RenderPassColorAttachment {
-                    view: &view,
-                    resolve_target: None,
-                    ops: wgpu::Operations {
-                        load: wgpu::LoadOp::Clear(wgpu::Color {
-                            r: 0.1,
-                            g: 0.2,
-                            b: 0.3,
-                            a: 1.0,
-                        }),
-                        store: wgpu::StoreOp::Store,
-                    },
-                })],
-                depth_stencil_attachment: None,
-                timestamp_writes: None,
-                occlusion_query_set: None,
-            });
+            puffin::profile_scope!("sprite upload");
+            let camera = self
+                .previous_camera
+                .interpolate(&self.camera, self.interpolation);
+            self.entity_sprites
+                .prepare(&self.device, &self.queue, &self.entities, &camera, aspect);
+            self.particles.prepare(&self.queue, &camera, aspect);
+        }
+        {
+            puffin::profile_scope!("scene encode");
+            if self.settings.render_scale == 1.0 {
+                self.scaled_target = None;
+            } else {
+                let max = self.device.limits().max_texture_dimension_2d;
+                let scale = self.settings.render_scale;
+                let width = render_scale::scaled_size(self.surface_config.width, scale, max);
+                let height = render_scale::scaled_size(self.surface_config.height, scale, max);
+                match &mut self.scaled_target {
+                    Some(target) => target.resize(&self.device, width, height),
+                    None => {
+                        self.scaled_target = Some(ScaledTarget::new(
+                            &self.device,
+                            self.surface_config.format,
+                            width,
+                            height,
+                        ))
+                    }
+                }
+            }
+            let scene_view = self.scaled_target.as_ref().map_or(&view, |t| t.view());
+            scene::draw(&mut encoder, scene_view, self.surface_config.format);
+            self.entity_sprites.draw(&mut encoder, scene_view);
+            self.particles.draw(&mut encoder, scene_view);
+            for plugin in &mut self.plugins {
+                plugin.render(&mut encoder, scene_view);
+            }
+            if let Some(target) = &self.scaled_target {
+                target.blit(&mut encoder, &view);
+            }
         }
 
         // Render the eGUI menu
+        let mut ui_mode = self.ui_mode;
+        let menu_choice;
+        let mut console_line = None;
+        let mut camera_jump = None;
         {
             let window = self.window.as_ref();
             let screen_descriptor = ScreenDescriptor {
                 size_in_pixels: [self.surface_config.width, self.surface_config.height],
-                pixels_per_point: window.scale_factor() as f32 * self.scale_factor,
+                pixels_per_point: window.scale_factor() as f32 * self.settings.ui_scale,
             };
 
+            puffin::profile_scope!("egui");
+            if self.applied_theme != Some(self.settings.theme) {
+                self.settings.theme.apply(self.egui_renderer.context());
+                self.applied_theme = Some(self.settings.theme);
+            }
             self.egui_renderer.begin_frame(window);
+            if !self.photo_mode {
+                self.touch_controls.draw(self.egui_renderer.context());
+            }
 
-            egui::Window::new("Controls")
-                .resizable(true)
-                .vscroll(true)
-                .default_open(true)
-                .show(self.egui_renderer.context(), |ui| {
-                    ui.label("Camera Controls");
-                    if ui.button("Reset Camera").clicked() {
-                        self.camera = Camera::new();
-                    }
-                    ui.horizontal(|ui| {
-                        ui.label(format!(
-                            "Pixels per point: {}",
-                            self.egui_renderer.context().pixels_per_point()
-                        ));
-                        if ui.button("-").clicked() {
-                            self.scale_factor = (self.scale_factor - 0.1).max(0.3);
+            let show_windows = self.shows_windows();
+            if show_windows && (self.game_state != GameState::MainMenu || self.show_settings) {
+                egui::Window::new("Controls")
+                    .resizable(true)
+                    .vscroll(true)
+                    .default_open(true)
+                    .show(self.egui_renderer.context(), |ui| {
+                        egui::CollapsingHeader::new("Performance")
+                            .default_open(true)
+                            .show(ui, |ui| self.frame_stats.ui(ui));
+                        ui.collapsing("GPU memory", |ui| {
+                            let mut tracked = vec![
+                                ("particles", self.particles.buffer_bytes()),
+                                ("entity sprites", self.entity_sprites.buffer_bytes()),
+                            ];
+                            if let Some(target) = &self.scaled_target {
+                                tracked.push(("scaled scene", target.texture_bytes()));
+                            }
+                            if let Some(timer) = &self.gpu_timer {
+                                tracked.push(("gpu timer", timer.buffer_bytes()));
+                            }
+                            self.gpu_memory.update(&self.device);
+                            self.gpu_memory.ui(ui, &self.device.limits(), &tracked);
+                        });
+                        ui.label(format!("Entities: {}", self.entities.len()));
+                        ui.checkbox(&mut self.particles.enabled, "Particles");
+                        ui.checkbox(&mut self.log_viewer.open, "Show log");
+                        let mut profiler_open = self.profiler.is_open();
+                        ui.horizontal(|ui| {
+                            if ui.checkbox(&mut profiler_open, "Profiler").changed() {
+                                self.profiler.set_open(profiler_open);
+                            }
+                            #[cfg(not(target_arch = "wasm32"))]
+                            if profiler_open {
+                                let label = if self.profiler_detached {
+                                    "Attach"
+                                } else {
+                                    "Detach"
+                                };
+                                if ui.button(label).clicked() {
+                                    self.profiler_detached = !self.profiler_detached;
+                                }
+                            }
+                        });
+                        ui.label("Camera Controls");
+                        ui.checkbox(&mut ui_mode, "UI mode");
+                        ui.collapsing("Graphics adapter", |ui| {
+                            let info = &self.adapter_info;
+                            egui::Grid::new("adapter_info").show(ui, |ui| {
+                                ui.label("Name");
+                                ui.label(&info.name);
+                                ui.end_row();
+                                ui.label("Backend");
+                                ui.label(format!("{:?}", info.backend));
+                                ui.end_row();
+                                ui.label("Type");
+                                ui.label(format!("{:?}", info.device_type));
+                                ui.end_row();
+                                ui.label("Driver");
+                                ui.label(format!("{} {}", info.driver, info.driver_info));
+                                ui.end_row();
+                            });
+                            egui::ComboBox::from_label("Switch adapter")
+                                .selected_text(format!("{} ({:?})", info.name, info.backend))
+                                .show_ui(ui, |ui| {
+                                    for (index, adapter) in self.adapters.iter().enumerate() {
+                                        let candidate = adapter.get_info();
+                                        let label =
+                                            format!("{} ({:?})", candidate.name, candidate.backend);
+                                        if ui.selectable_label(candidate == *info, label).clicked()
+                                            && candidate != *info
+                                        {
+                                            self.pending_adapter = Some(index);
+                                        }
+                                    }
+                                });
+                        });
+                        ui.collapsing("Navigation", |ui| {
+                            if let Some(position) =
+                                self.navigation.ui(ui, self.camera.get_position())
+                            {
+                                let mut camera = self.camera.clone();
+                                camera.set_position(position);
+                                camera_jump = Some(camera);
+                            }
+                        });
+                        ui.collapsing("Theme", |ui| {
+                            let theme = &mut self.settings.theme;
+                            ui.horizontal(|ui| {
+                                ui.selectable_value(&mut theme.preset, ThemePreset::Dark, "Dark");
+                                ui.selectable_value(&mut theme.preset, ThemePreset::Light, "Light");
+                            });
+                            ui.horizontal(|ui| {
+                                ui.color_edit_button_srgb(&mut theme.accent);
+                                ui.label("Accent");
+                            });
+                            ui.add(
+                                egui::Slider::new(&mut theme.font_size, 8.0..=24.0)
+                                    .text("Font size"),
+                            );
+                            if ui.button("Reset theme").clicked() {
+                                *theme = ThemeSettings::default();
+                            }
+                        });
+                        ui.collapsing("Mouse", |ui| {
+                            let settings = &mut self.settings.mouse;
+                            ui.checkbox(&mut settings.raw_motion, "Raw input");
+                            ui.checkbox(&mut settings.invert_y, "Invert Y");
+                            ui.checkbox(&mut settings.lock_on_click, "Lock cursor on click")
+                                .on_hover_text(
+                                    "When off, hold the right mouse button to look around",
+                                );
+                        });
+                        let camera_settings = &mut self.settings.camera;
+                        let speed = ui.add(
+                            egui::Slider::new(&mut camera_settings.speed, 0.1..=10.0).text("Speed"),
+                        );
+                        let sensitivity = ui.add(
+                            egui::Slider::new(&mut camera_settings.sensitivity, 0.05..=5.0)
+                                .text("Sensitivity"),
+                        );
+                        if speed.changed() || sensitivity.changed() {
+                            self.camera.apply_settings(camera_settings);
                         }
-                        if ui.button("+").clicked() {
-                            self.scale_factor = (self.scale_factor + 0.1).min(3.0);
+                        if ui.button("Reset Camera").clicked() {
+                            camera_jump = Some(Camera::new(&self.settings.camera));
+                        }
+                        ui.horizontal(|ui| {
+                            ui.label(format!(
+                                "Pixels per point: {}",
+                                self.egui_renderer.context().pixels_per_point()
+                            ));
+                            if ui.button("-").clicked() {
+                                self.settings.ui_scale = (self.settings.ui_scale - 0.1).max(0.3);
+                            }
+                            if ui.button("+").clicked() {
+                                self.settings.ui_scale = (self.settings.ui_scale + 0.1).min(3.0);
+                            }
+                        });
+                        ui.checkbox(&mut self.settings.low_power, "Low power")
+                            .on_hover_text(
+                                "Only redraw when the camera, the scene or the UI changes",
+                            );
+                        ui.add(
+                            egui::Slider::new(&mut self.settings.fps_limit, 0..=240)
+                                .text("FPS limit"),
+                        )
+                        .on_hover_text("0 means no limit besides the present mode");
+                        let quality = self.settings.quality.unwrap_or(QualityPreset::Custom);
+                        egui::ComboBox::from_label("Quality")
+                            .selected_text(format!("{quality:?}"))
+                            .show_ui(ui, |ui| {
+                                for preset in QualityPreset::ALL {
+                                    if ui
+                                        .selectable_label(quality == preset, format!("{preset:?}"))
+                                        .clicked()
+                                    {
+                                        self.settings.apply_quality(preset);
+                                    }
+                                }
+                            });
+                        let render_scale = ui
+                            .add(
+                                egui::Slider::new(&mut self.settings.render_scale, 0.25..=2.0)
+                                    .text("Render scale"),
+                            )
+                            .on_hover_text("Resolution of the scene relative to the window");
+                        if render_scale.changed() {
+                            self.settings.quality = Some(QualityPreset::Custom);
+                        }
+                        ui.collapsing("Present mode", |ui| {
+                            let current = self.settings.present_mode;
+                            egui::ComboBox::from_label("Mode")
+                                .selected_text(format!("{current:?}"))
+                                .show_ui(ui, |ui| {
+                                    for mode in PresentMode::ALL {
+                                        let supported =
+                                            self.present_modes.contains(&mode.to_wgpu());
+                                        ui.add_enabled_ui(supported, |ui| {
+                                            ui.selectable_value(
+                                                &mut self.settings.present_mode,
+                                                mode,
+                                                format!("{mode:?}"),
+                                            );
+                                        });
+                                    }
+                                });
+                            if self.settings.present_mode != current {
+                                let measured = (
+                                    current,
+                                    self.frame_stats.fps(),
+                                    self.frame_stats.average_latency_ms(),
+                                );
+                                self.present_mode_results
+                                    .retain(|(mode, ..)| *mode != current);
+                                self.present_mode_results.push(measured);
+                                self.frame_stats.clear();
+
+                                self.surface_config.present_mode =
+                                    self.settings.present_mode.to_wgpu();
+                                self.surface.configure(&self.device, &self.surface_config);
+                            }
+
+                            egui::Grid::new("present_mode_results").show(ui, |ui| {
+                                for (mode, fps, latency) in &self.present_mode_results {
+                                    ui.label(format!("{mode:?}"));
+                                    ui.label(format!("{fps:.0} FPS"));
+                                    ui.label(format!("{latency:.2} ms"));
+                                    ui.end_row();
+                                }
+                            });
+                        });
+                        ui.collapsing("Input recording", |ui| {
+                            if let Some(recorder) = &self.recorder {
+                                ui.label(format!("Recording: {} frames", recorder.frame_count()));
+                                if ui.button("Stop and save").clicked() {
+                                    if let Err(err) = recorder.save(RECORDING_PATH) {
+                                        tracing::error!("Failed to save {RECORDING_PATH}: {err}");
+                                    }
+                                    self.recorder = None;
+                                }
+                            } else if let Some(playback) = &self.playback {
+                                ui.label(format!(
+                                    "Replaying: {} frames left",
+                                    playback.remaining()
+                                ));
+                                if ui.button("Stop").clicked() {
+                                    self.playback = None;
+                                }
+                            } else {
+                                ui.horizontal(|ui| {
+                                    if ui.button("Record").clicked() {
+                                        self.recorder = Some(InputRecorder::new(&self.camera));
+                                    }
+                                    if ui.button("Replay").clicked() {
+                                        match InputPlayback::load(RECORDING_PATH) {
+                                            Ok((camera, playback)) => {
+                                                camera_jump = Some(camera);
+                                                self.playback = Some(playback);
+                                            }
+                                            Err(err) => {
+                                                tracing::error!(
+                                                    "Failed to load {RECORDING_PATH}: {err}"
+                                                )
+                                            }
+                                        }
+                                    }
+                                });
+                            }
+                        });
+                        ui.collapsing("Hotkeys", |ui| {
+                            egui::Grid::new("hotkeys").striped(true).show(ui, |ui| {
+                                for binding in self.settings.keybinds.bindings() {
+                                    ui.label(binding.chord.to_string());
+                                    ui.label(binding.action.label());
+                                    if let Some(context) = binding.context {
+                                        ui.weak(format!("{context:?} only"));
+                                    }
+                                    ui.end_row();
+                                }
+                            });
+                            for (first, second) in self.settings.keybinds.conflicts() {
+                                ui.colored_label(
+                                    ui.visuals().warn_fg_color,
+                                    format!(
+                                        "{} is bound to both {} and {}",
+                                        first.chord,
+                                        first.action.label(),
+                                        second.action.label()
+                                    ),
+                                );
+                            }
+                        });
+                        if let Some(tap) = self.last_tap {
+                            ui.label(format!("Last tap: ({:.0}, {:.0})", tap.x, tap.y));
                         }
                     });
-                });
+            }
+            menu_choice = match self.game_state {
+                GameState::MainMenu => {
+                    game_state::main_menu(self.egui_renderer.context(), &self.scene_files)
+                }
+                GameState::Paused if show_windows => {
+                    game_state::pause_overlay(self.egui_renderer.context())
+                }
+                _ => None,
+            };
+            if show_windows {
+                if self.game_state != GameState::MainMenu {
+                    self.navigation
+                        .hud(self.egui_renderer.context(), self.camera.get_position());
+                }
+                if self.log_viewer.open {
+                    self.log_viewer.ui(self.egui_renderer.context());
+                }
+                if !self.profiler_detached {
+                    self.profiler.ui(self.egui_renderer.context());
+                }
+                console_line = self
+                    .console
+                    .ui(self.egui_renderer.context(), CONSOLE_COMMANDS);
+                for plugin in &mut self.plugins {
+                    plugin.ui(self.egui_renderer.context());
+                }
+            }
 
-            self.egui_renderer.end_frame_and_draw(
+            self.egui_repaint_delay = self.egui_renderer.end_frame_and_draw(
                 &self.device,
                 &self.queue,
                 &mut encoder,
@@ -176,117 +1106,874 @@ impl AppState {
             );
         }
 
-        self.queue.submit(Some(encoder.finish()));
-        surface_texture.present();
+        if ui_mode != self.ui_mode {
+            self.set_ui_mode(ui_mode);
+        }
+        if let Some(camera) = camera_jump {
+            self.set_camera_discontinuous(camera);
+        }
+        match menu_choice {
+            Some(MenuChoice::Play) => {
+                self.set_camera_discontinuous(Camera::new(&self.settings.camera));
+                self.show_settings = false;
+                self.set_game_state(GameState::Playing);
+            }
+            Some(MenuChoice::LoadScene(index)) => {
+                let path = self.scene_files[index].clone();
+                match SceneFile::load(&path) {
+                    Ok(scene) => {
+                        self.set_camera_discontinuous(Camera::new(&self.settings.camera));
+                        self.apply_scene(&scene);
+                        self.show_settings = false;
+                        self.set_game_state(GameState::Playing);
+                    }
+                    Err(err) => {
+                        tracing::error!("Failed to load scene {}: {err}", path.display());
+                        self.console
+                            .print(&format!("Error: failed to load {}: {err}", path.display()));
+                    }
+                }
+            }
+            Some(MenuChoice::Resume) => self.set_game_state(GameState::Playing),
+            Some(MenuChoice::Settings) => self.show_settings = !self.show_settings,
+            Some(MenuChoice::MainMenu) => self.set_game_state(GameState::MainMenu),
+            Some(MenuChoice::Quit) => self.exit_requested = true,
+            None => {}
+        }
+        if let Some(line) = console_line {
+            let output = console::execute(self, CONSOLE_COMMANDS, &line)
+                .unwrap_or_else(|err| format!("Error: {err}"));
+            self.console.print(&output);
+        }
+
+        if let Some(timer) = self.gpu_timer.as_mut() {
+            timer.end(&mut encoder);
+        }
+        {
+            puffin::profile_scope!("submit");
+            self.queue.submit(Some(encoder.finish()));
+        }
+        if let Some(timer) = self.gpu_timer.as_mut() {
+            timer.submitted();
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(path) = self.screenshot_path.take() {
+            self.save_screenshot(&surface_texture.texture, &path);
+        }
+        let suboptimal = surface_texture.suboptimal;
+        {
+            puffin::profile_scope!("present");
+            surface_texture.present();
+        }
+        if suboptimal {
+            self.reconfigure_surface();
+        }
+
+        Ok(())
+    }
+}
+
+const CONSOLE_COMMANDS: &[Command<AppState>] = &[
+    Command {
+        name: "tp",
+        usage: "tp <x> <y> <z>",
+        help: "Move the camera to a position",
+        run: teleport,
+    },
+    Command {
+        name: "look",
+        usage: "look <x> <y> <z>",
+        help: "Point the camera at a position",
+        run: look_at,
+    },
+    Command {
+        name: "stats",
+        usage: "stats",
+        help: "Print frame timings and counters",
+        run: print_stats,
+    },
+    Command {
+        name: "waypoint",
+        usage: "waypoint <add|go|remove> <name>",
+        help: "Save the camera position under a name or return to it",
+        run: waypoint_command,
+    },
+    Command {
+        name: "scene",
+        usage: "scene <save|load> <name>",
+        help: "Save or load the camera, particles and entities in scenes/",
+        run: scene_command,
+    },
+    Command {
+        name: "screenshot",
+        usage: "screenshot [name]",
+        help: "Save the next frame as a PNG in screenshots/",
+        run: take_screenshot,
+    },
+];
+
+fn parse_vec3(args: &[&str]) -> Result<glam::Vec3, String> {
+    let [x, y, z] = args else {
+        return Err("expected three coordinates".to_owned());
+    };
+    let parse = |value: &str| {
+        value
+            .parse::<f32>()
+            .map_err(|_| format!("`{value}` is not a number"))
+    };
+    Ok(glam::Vec3::new(parse(x)?, parse(y)?, parse(z)?))
+}
+
+fn teleport(state: &mut AppState, args: &[&str]) -> Result<String, String> {
+    let position = parse_vec3(args)?;
+    let mut camera = state.camera.clone();
+    camera.set_position(position);
+    state.set_camera_discontinuous(camera);
+    Ok(format!("Teleported to {position}"))
+}
+
+fn look_at(state: &mut AppState, args: &[&str]) -> Result<String, String> {
+    let target = parse_vec3(args)?;
+    let mut camera = state.camera.clone();
+    camera.look_at(target);
+    state.set_camera_discontinuous(camera);
+    Ok(format!("Looking at {target}"))
+}
+
+fn waypoint_command(state: &mut AppState, args: &[&str]) -> Result<String, String> {
+    match args {
+        ["add", name] => {
+            let position = state.camera.get_position();
+            state.navigation.add(name, position);
+            Ok(format!("Added waypoint {name} at {position}"))
+        }
+        ["go", name] => {
+            let waypoint = state
+                .navigation
+                .find(name)
+                .ok_or_else(|| format!("no waypoint named `{name}`"))?;
+            let mut camera = state.camera.clone();
+            camera.set_position(waypoint.position);
+            state.set_camera_discontinuous(camera);
+            Ok(format!("Teleported to {name}"))
+        }
+        ["remove", name] => {
+            let count = state.navigation.waypoints.len();
+            state
+                .navigation
+                .waypoints
+                .retain(|waypoint| waypoint.name != *name);
+            if state.navigation.waypoints.len() == count {
+                return Err(format!("no waypoint named `{name}`"));
+            }
+            Ok(format!("Removed waypoint {name}"))
+        }
+        _ => Err("expected `add`, `go` or `remove` and a name".to_owned()),
+    }
+}
+
+/// Resolves a file name given to a console command inside `dir`. Absolute paths
+/// and `..` are rejected, so remote clients can't reach files anywhere else.
+fn path_in(dir: &str, name: &str) -> Result<PathBuf, String> {
+    let relative = Path::new(name);
+    let inside = relative
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)));
+    if name.is_empty() || !inside {
+        return Err(format!("`{name}` must be a relative path inside {dir}/"));
+    }
+    Ok(Path::new(dir).join(relative))
+}
+
+fn scene_command(state: &mut AppState, args: &[&str]) -> Result<String, String> {
+    match args {
+        ["save", name] => {
+            let path = path_in(SCENE_DIR, name)?;
+            if let Some(parent) = path.parent() {
+                std::fs::create_dir_all(parent)
+                    .map_err(|err| format!("failed to create {}: {err}", parent.display()))?;
+            }
+            state
+                .capture_scene()
+                .save(&path)
+                .map_err(|err| format!("failed to save {}: {err}", path.display()))?;
+            Ok(format!("Saved scene to {}", path.display()))
+        }
+        ["load", name] => {
+            let path = path_in(SCENE_DIR, name)?;
+            let scene = SceneFile::load(&path)
+                .map_err(|err| format!("failed to load {}: {err}", path.display()))?;
+            state.apply_scene(&scene);
+            Ok(format!("Loaded scene from {}", path.display()))
+        }
+        _ => Err("expected `save <name>` or `load <name>`".to_owned()),
+    }
+}
+
+fn print_stats(state: &mut AppState, _args: &[&str]) -> Result<String, String> {
+    Ok(format!(
+        "FPS {:.0} (1% low {:.0}), latency {:.2} ms\nEntities {}\nCamera at {}",
+        state.frame_stats.fps(),
+        state.frame_stats.one_percent_low(),
+        state.frame_stats.average_latency_ms(),
+        state.entities.len(),
+        state.camera.get_position(),
+    ))
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+fn take_screenshot(state: &mut AppState, args: &[&str]) -> Result<String, String> {
+    let path = path_in(
+        SCREENSHOT_DIR,
+        args.first().copied().unwrap_or("screenshot.png"),
+    )?;
+    let message = format!("Saving screenshot to {}", path.display());
+    state.screenshot_path = Some(path);
+    Ok(message)
+}
+
+#[cfg(target_arch = "wasm32")]
+fn take_screenshot(_state: &mut AppState, _args: &[&str]) -> Result<String, String> {
+    Err("screenshots aren't supported in the browser".to_owned())
+}
+
+async fn create_device(adapter: &wgpu::Adapter) -> (wgpu::Device, wgpu::Queue) {
+    adapter
+        .request_device(
+            &wgpu::DeviceDescriptor {
+                label: None,
+                required_features: adapter.features() & wgpu::Features::TIMESTAMP_QUERY,
+                required_limits: wgpu::Limits::default(),
+                memory_hints: wgpu::MemoryHints::default(),
+            },
+            None,
+        )
+        .await
+        .expect("Failed to create device")
+}
+
+fn create_surface_config(
+    adapter: &wgpu::Adapter,
+    surface: &wgpu::Surface,
+    width: u32,
+    height: u32,
+    present_mode: PresentMode,
+) -> wgpu::SurfaceConfiguration {
+    let swapchain_capabilities = surface.get_capabilities(adapter);
+    let swapchain_format = select_surface_format(&swapchain_capabilities.formats);
+
+    wgpu::SurfaceConfiguration {
+        // Screenshots copy straight from the surface texture where that is supported.
+        usage: wgpu::TextureUsages::RENDER_ATTACHMENT
+            | (swapchain_capabilities.usages & wgpu::TextureUsages::COPY_SRC),
+        format: swapchain_format,
+        width,
+        height,
+        present_mode: supported_present_mode(&swapchain_capabilities, present_mode),
+        desired_maximum_frame_latency: 2,
+        alpha_mode: swapchain_capabilities.alpha_modes[0],
+        view_formats: vec![],
+    }
+}
+
+/// Prefers 8-bit sRGB formats, then 8-bit UNORM ones (the particle shader and
+/// clear color encode sRGB themselves there), then whatever the surface offers.
+/// Block-compressed formats can't be rendered to and are never picked.
+pub fn select_surface_format(formats: &[wgpu::TextureFormat]) -> wgpu::TextureFormat {
+    const PREFERRED: [wgpu::TextureFormat; 4] = [
+        wgpu::TextureFormat::Bgra8UnormSrgb,
+        wgpu::TextureFormat::Rgba8UnormSrgb,
+        wgpu::TextureFormat::Bgra8Unorm,
+        wgpu::TextureFormat::Rgba8Unorm,
+    ];
+    let renderable = || {
+        formats
+            .iter()
+            .copied()
+            .filter(|format| !format.is_compressed())
+    };
+    let format = PREFERRED
+        .into_iter()
+        .find(|format| formats.contains(format))
+        .or_else(|| renderable().find(|format| format.is_srgb()))
+        .or_else(|| renderable().next())
+        .expect("The surface doesn't support any renderable texture format");
+    if !format.is_srgb() {
+        tracing::info!(
+            "No sRGB surface format available, converting to sRGB in shaders ({format:?})"
+        );
+    }
+    format
+}
+
+/// Falls back to Fifo, which every surface supports, if the preferred mode isn't available.
+fn supported_present_mode(
+    capabilities: &wgpu::SurfaceCapabilities,
+    preferred: PresentMode,
+) -> wgpu::PresentMode {
+    let mode = preferred.to_wgpu();
+    if capabilities.present_modes.contains(&mode) {
+        mode
+    } else {
+        wgpu::PresentMode::Fifo
     }
 }
 
+/// The winit application handler: owns the window and paces updates and redraws.
 pub struct App {
     instance: wgpu::Instance,
+    /// Delivers the state once it has been created asynchronously in the browser.
+    #[cfg(target_arch = "wasm32")]
+    proxy: winit::event_loop::EventLoopProxy<AppState>,
     state: Option<AppState>,
     window: Option<Arc<Window>>,
+    last_frame: Option<Instant>,
+    tick_accumulator: Duration,
+    /// Consecutive frames that timed out acquiring the surface texture.
+    surface_timeouts: u32,
+    /// Rendering is paused until then after a surface timeout.
+    surface_retry_at: Option<Instant>,
+    minimized: bool,
+    occluded: bool,
+    /// Low power mode is waiting for input before drawing the next frame.
+    idle: bool,
+    /// Plugins added before the state exists, handed over once it does.
+    plugins: Vec<Box<dyn EnginePlugin>>,
+    benchmark: Option<Benchmark>,
+    frame_limiter: FrameLimiter,
+    #[cfg(not(target_arch = "wasm32"))]
+    remote: Option<RemoteControl>,
+    /// When the event loop wakes up to poll `remote`, and the control flow
+    /// that deadline cut short. Waking up for it doesn't draw a frame.
+    #[cfg(not(target_arch = "wasm32"))]
+    remote_poll: Option<(Instant, ControlFlow)>,
+    #[cfg(not(target_arch = "wasm32"))]
+    profiler_window: Option<ToolWindow>,
 }
 
 impl App {
-    pub fn new() -> Self {
+    /// The event loop's user events deliver the [`AppState`] once the window's
+    /// device has been created.
+    pub fn new(event_loop: &EventLoop<AppState>) -> Self {
         let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        #[cfg(not(target_arch = "wasm32"))]
+        let _ = event_loop;
         Self {
             instance,
+            #[cfg(target_arch = "wasm32")]
+            proxy: event_loop.create_proxy(),
             state: None,
             window: None,
+            last_frame: None,
+            tick_accumulator: Duration::ZERO,
+            surface_timeouts: 0,
+            surface_retry_at: None,
+            minimized: false,
+            occluded: false,
+            idle: false,
+            plugins: Vec::new(),
+            benchmark: None,
+            frame_limiter: FrameLimiter::new(),
+            #[cfg(not(target_arch = "wasm32"))]
+            remote: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            remote_poll: None,
+            #[cfg(not(target_arch = "wasm32"))]
+            profiler_window: None,
+        }
+    }
+
+    /// Flies the benchmark's camera path instead of taking input, then writes a
+    /// report and exits.
+    pub fn with_benchmark(mut self, scene: BenchScene) -> Self {
+        self.benchmark = Some(Benchmark::new(scene));
+        self
+    }
+
+    /// Runs console commands received by `remote`, also while the window is hidden.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn with_remote_control(mut self, remote: RemoteControl) -> Self {
+        self.remote = Some(remote);
+        self
+    }
+
+    pub fn add_plugin(&mut self, plugin: impl EnginePlugin + 'static) {
+        match self.state.as_mut() {
+            Some(state) => state.add_plugin(Box::new(plugin)),
+            None => self.plugins.push(Box::new(plugin)),
+        }
+    }
+
+    fn set_state(&mut self, mut state: AppState) {
+        for plugin in self.plugins.drain(..) {
+            state.add_plugin(plugin);
         }
+        self.state = Some(state);
     }
 
-    async fn set_window(&mut self, window: Window) {
+    fn set_window(&mut self, window: Window) {
         let window = Arc::new(window);
-        let initial_width = 1360;
-        let initial_height = 768;
+        self.window = Some(window.clone());
 
-        let _ = window.request_inner_size(PhysicalSize::new(initial_width, initial_height));
+        // Blocking on the device request isn't possible in the browser, so the
+        // state arrives later as a user event there.
+        #[cfg(not(target_arch = "wasm32"))]
+        {
+            let state = pollster::block_on(AppState::for_window(self.instance.clone(), window));
+            self.set_state(state);
+        }
+        #[cfg(target_arch = "wasm32")]
+        {
+            let instance = self.instance.clone();
+            let proxy = self.proxy.clone();
+            wasm_bindgen_futures::spawn_local(async move {
+                let state = AppState::for_window(instance, window).await;
+                let _ = proxy.send_event(state);
+            });
+        }
+    }
 
-        let surface = self
-            .instance
-            .create_surface(window.clone())
-            .expect("Failed to create surface!");
+    /// Runs the commands remote clients sent since the last call.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn poll_remote(&mut self, event_loop: &ActiveEventLoop) {
+        let (Some(remote), Some(state)) = (&self.remote, self.state.as_mut()) else {
+            return;
+        };
+        let mut handled = false;
+        for request in remote.poll() {
+            let result = console::execute(state, CONSOLE_COMMANDS, &request.line);
+            request.respond(result);
+            handled = true;
+        }
+        if handled && self.is_visible() {
+            self.window.as_ref().unwrap().request_redraw();
+        }
 
-        let state = AppState::new(
-            &self.instance,
-            surface,
-            window.clone(),
-            initial_width,
-            initial_height,
-        )
-        .await;
+        // Nothing else changed the control flow since the last poll, so
+        // continue with what it replaced.
+        let control_flow = match (event_loop.control_flow(), self.remote_poll.take()) {
+            (ControlFlow::WaitUntil(deadline), Some((poll_at, replaced)))
+                if deadline == poll_at =>
+            {
+                replaced
+            }
+            (control_flow, _) => control_flow,
+        };
+        let poll_at = Instant::now() + REMOTE_POLL_INTERVAL;
+        let sleeps_longer = match control_flow {
+            ControlFlow::Wait => true,
+            ControlFlow::WaitUntil(deadline) => deadline > poll_at,
+            ControlFlow::Poll => false,
+        };
+        if sleeps_longer {
+            self.remote_poll = Some((poll_at, control_flow));
+            event_loop.set_control_flow(ControlFlow::WaitUntil(poll_at));
+        } else {
+            event_loop.set_control_flow(control_flow);
+        }
+    }
 
-        self.window = Some(window);
-        self.state = Some(state);
+    fn is_visible(&self) -> bool {
+        !self.minimized && !self.occluded
+    }
+
+    /// Stops the redraw loop while the window can't be seen and restarts it once it can.
+    fn update_visibility(&mut self, event_loop: &ActiveEventLoop, was_visible: bool) {
+        let visible = self.is_visible();
+        if visible == was_visible {
+            return;
+        }
+
+        if visible {
+            // Don't count the hidden time as a frame or let the simulation catch up on it.
+            self.last_frame = None;
+            self.tick_accumulator = Duration::ZERO;
+            event_loop.set_control_flow(ControlFlow::Poll);
+            self.window.as_ref().unwrap().request_redraw();
+        } else {
+            event_loop.set_control_flow(ControlFlow::Wait);
+        }
     }
 
-    fn handle_resized(&mut self, width: u32, height: u32) {
-        if width > 0 && height > 0 {
+    fn handle_resized(&mut self, event_loop: &ActiveEventLoop, width: u32, height: u32) {
+        let was_visible = self.is_visible();
+        self.minimized = width == 0 || height == 0;
+        if !self.minimized {
             self.state.as_mut().unwrap().resize_surface(width, height);
+            // The new size is applied by the next frame, even in low power mode.
+            self.window.as_ref().unwrap().request_redraw();
+        }
+        self.update_visibility(event_loop, was_visible);
+    }
+
+    fn handle_occluded(&mut self, event_loop: &ActiveEventLoop, occluded: bool) {
+        let was_visible = self.is_visible();
+        self.occluded = occluded;
+        self.update_visibility(event_loop, was_visible);
+    }
+
+    fn handle_redraw(&mut self, event_loop: &ActiveEventLoop) {
+        if let Some(wake_at) = self.frame_limiter.wait() {
+            // Woken before the next frame is due; it's drawn once it is.
+            event_loop.set_control_flow(ControlFlow::WaitUntil(wake_at));
+            return;
+        }
+        let now = Instant::now();
+        if let Some(retry_at) = self.surface_retry_at {
+            if now < retry_at {
+                event_loop.set_control_flow(ControlFlow::WaitUntil(retry_at));
+                return;
+            }
+            self.surface_retry_at = None;
+            event_loop.set_control_flow(ControlFlow::Poll);
+        }
+        if self.idle {
+            // Waking up from low power mode: the wait wasn't a frame.
+            self.idle = false;
+            self.last_frame = None;
+            self.tick_accumulator = Duration::ZERO;
+        }
+
+        let frame_time = self
+            .last_frame
+            .map_or(FIXED_TIMESTEP, |last| now.duration_since(last));
+        self.last_frame = Some(now);
+        self.tick_accumulator += frame_time;
+        Profiler::new_frame();
+
+        let state = self.state.as_mut().unwrap();
+        if let Some(index) = state.pending_adapter.take() {
+            let adapter = state.adapters[index].clone();
+            state.switch_adapter(adapter);
+            // Reopened on the new device below.
+            #[cfg(not(target_arch = "wasm32"))]
+            {
+                self.profiler_window = None;
+            }
+        }
+
+        if let Some(benchmark) = &self.benchmark {
+            if state.game_state != GameState::Playing {
+                state.set_game_state(GameState::Playing);
+            }
+            let (position, target) = benchmark.camera_pose();
+            let mut camera = state.camera.clone();
+            camera.set_position(position);
+            camera.look_at(target);
+            state.set_camera_discontinuous(camera);
+        }
+
+        let cpu_start = Instant::now();
+        let mut ticks = 0;
+        while self.tick_accumulator >= FIXED_TIMESTEP {
+            if ticks == MAX_TICKS_PER_FRAME {
+                self.tick_accumulator = Duration::ZERO;
+                break;
+            }
+            state.update();
+            self.tick_accumulator -= FIXED_TIMESTEP;
+            ticks += 1;
+        }
+        state.set_interpolation(self.tick_accumulator.as_secs_f32() / FIXED_TIMESTEP.as_secs_f32());
+
+        match state.render() {
+            Ok(()) => self.surface_timeouts = 0,
+            Err(wgpu::SurfaceError::Timeout) => {
+                // Back off exponentially so a stalled compositor isn't hammered every frame.
+                let backoff = (FIXED_TIMESTEP * 2u32.pow(self.surface_timeouts.min(6)))
+                    .min(MAX_SURFACE_BACKOFF);
+                let retry_at = Instant::now() + backoff;
+                self.surface_timeouts += 1;
+                self.surface_retry_at = Some(retry_at);
+                event_loop.set_control_flow(ControlFlow::WaitUntil(retry_at));
+                return;
+            }
+            Err(wgpu::SurfaceError::OutOfMemory) => {
+                tracing::error!("Out of memory while acquiring the surface texture, shutting down");
+                state.save_settings(true);
+                event_loop.exit();
+                return;
+            }
+            Err(err) => tracing::warn!("Dropped frame: {err}"),
+        }
+
+        if state.exit_requested {
+            state.save_settings(true);
+            event_loop.exit();
+            return;
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        self.update_profiler_window(event_loop);
+        let state = self.state.as_mut().unwrap();
+
+        let cpu_time = cpu_start.elapsed().saturating_sub(state.surface_wait);
+        let gpu_time = state
+            .gpu_timer
+            .as_mut()
+            .and_then(|timer| timer.poll(&state.device));
+        state
+            .frame_stats
+            .push(frame_time, cpu_time, gpu_time, now.elapsed());
+        state.save_settings(false);
+
+        if let Some(benchmark) = self.benchmark.as_mut() {
+            benchmark.record(frame_time, cpu_time, gpu_time);
+            if benchmark.is_finished() {
+                let path = benchmark.report_path();
+                let (fps, one_percent_low) = benchmark.summary();
+                match benchmark.write_report(&path) {
+                    Ok(()) => tracing::info!(
+                        "Benchmark {}: {fps:.1} FPS, 1% low {one_percent_low:.1} FPS, report written to {}",
+                        benchmark.scene.name(),
+                        path.display()
+                    ),
+                    Err(err) => tracing::error!("Failed to write {}: {err}", path.display()),
+                }
+                event_loop.exit();
+                return;
+            }
+        }
+
+        let delay = state.egui_repaint_delay;
+        let fps_limit = state.settings.fps_limit;
+        let keep_drawing = !state.settings.low_power
+            || state.is_active()
+            || delay.is_zero()
+            || self.benchmark.is_some();
+        if !self.is_visible() {
+            return;
+        }
+        if keep_drawing {
+            match self.frame_limiter.schedule(fps_limit) {
+                // The redraw is requested once the wait is over, in `new_events`.
+                Some(wake_at) => event_loop.set_control_flow(ControlFlow::WaitUntil(wake_at)),
+                None => {
+                    event_loop.set_control_flow(ControlFlow::Poll);
+                    self.window.as_ref().unwrap().request_redraw();
+                }
+            }
+        } else {
+            self.idle = true;
+            event_loop.set_control_flow(match Instant::now().checked_add(delay) {
+                Some(deadline) => ControlFlow::WaitUntil(deadline),
+                None => ControlFlow::Wait,
+            });
         }
     }
 
-    fn handle_redraw(&mut self) {
+    /// Opens or closes the detached profiler window to match the state, then
+    /// asks it to show the frame that was just profiled.
+    #[cfg(not(target_arch = "wasm32"))]
+    fn update_profiler_window(&mut self, event_loop: &ActiveEventLoop) {
         let state = self.state.as_mut().unwrap();
-        state.camera.handle_input(&state.pressed_keys);
+        if !state.profiler.is_open() {
+            state.profiler_detached = false;
+        }
+        if !state.profiler_detached {
+            self.profiler_window = None;
+            return;
+        }
 
-        if state.cursor_locked && state.mouse_delta != (0.0, 0.0) {
-            state
-                .camera
-                .handle_mouse(&(state.mouse_delta.0 as f64, state.mouse_delta.1 as f64));
-            state.mouse_delta = (0.0, 0.0); 
+        if self.profiler_window.is_none() {
+            let attributes = Window::default_attributes()
+                .with_title("Profiler")
+                .with_inner_size(PhysicalSize::new(900, 400));
+            let window = match event_loop.create_window(attributes) {
+                Ok(window) => Arc::new(window),
+                Err(err) => {
+                    tracing::warn!("Failed to open the profiler window: {err}");
+                    state.profiler_detached = false;
+                    return;
+                }
+            };
+            match ToolWindow::new(&self.instance, &state.adapter, &state.device, window) {
+                Some(tool_window) => self.profiler_window = Some(tool_window),
+                None => {
+                    tracing::warn!("The graphics adapter can't present to the profiler window");
+                    state.profiler_detached = false;
+                    return;
+                }
+            }
+        }
+
+        if let Some(tool_window) = &self.profiler_window {
+            tool_window.request_redraw();
         }
+    }
 
-        state.render();
-        self.window.as_ref().unwrap().request_redraw();
+    /// Wakes low power mode up for input that may change what is on screen.
+    fn wake(&self) {
+        if self.idle {
+            self.window.as_ref().unwrap().request_redraw();
+        }
     }
 }
 
-impl ApplicationHandler for App {
+impl ApplicationHandler<AppState> for App {
+    fn new_events(&mut self, _event_loop: &ActiveEventLoop, cause: StartCause) {
+        if let StartCause::ResumeTimeReached {
+            requested_resume, ..
+        } = cause
+        {
+            #[cfg(not(target_arch = "wasm32"))]
+            if self
+                .remote_poll
+                .is_some_and(|(poll_at, _)| poll_at == requested_resume)
+            {
+                return;
+            }
+            #[cfg(target_arch = "wasm32")]
+            let _ = requested_resume;
+            if let Some(window) = self.window.as_ref().filter(|_| self.is_visible()) {
+                window.request_redraw();
+            }
+        }
+    }
+
+    fn about_to_wait(&mut self, event_loop: &ActiveEventLoop) {
+        #[cfg(not(target_arch = "wasm32"))]
+        self.poll_remote(event_loop);
+        #[cfg(target_arch = "wasm32")]
+        let _ = event_loop;
+    }
+
     fn resumed(&mut self, event_loop: &ActiveEventLoop) {
-        let window = event_loop
-            .create_window(Window::default_attributes())
-            .unwrap();
-        pollster::block_on(self.set_window(window));
+        if self.window.is_some() {
+            return;
+        }
+
+        #[allow(unused_mut)]
+        let mut attributes = Window::default_attributes();
+        #[cfg(target_arch = "wasm32")]
+        {
+            use winit::platform::web::WindowAttributesExtWebSys;
+            attributes = attributes.with_append(true);
+        }
+        let window = event_loop.create_window(attributes).unwrap();
+        self.set_window(window);
     }
 
-    fn window_event(&mut self, event_loop: &ActiveEventLoop, _: WindowId, event: WindowEvent) {
-        let state = self.state.as_mut().unwrap();
-        state
+    fn user_event(&mut self, _event_loop: &ActiveEventLoop, state: AppState) {
+        self.set_state(state);
+        if let Some(window) = self.window.as_ref() {
+            window.request_redraw();
+        }
+    }
+
+    fn window_event(&mut self, event_loop: &ActiveEventLoop, id: WindowId, event: WindowEvent) {
+        let Some(state) = self.state.as_mut() else {
+            return;
+        };
+        #[cfg(not(target_arch = "wasm32"))]
+        if let Some(tool_window) = self.profiler_window.as_mut() {
+            if tool_window.id() == id {
+                match event {
+                    WindowEvent::CloseRequested => {
+                        state.profiler_detached = false;
+                        self.profiler_window = None;
+                    }
+                    // Drawn by itself, so it stays live while the main window is
+                    // hidden or idle.
+                    WindowEvent::RedrawRequested => {
+                        let profiler = &mut state.profiler;
+                        tool_window.set_theme(state.settings.theme);
+                        tool_window
+                            .render(&state.device, &state.queue, |ctx| profiler.panel_ui(ctx));
+                    }
+                    _ => {
+                        if tool_window.handle_event(&state.device, &event) {
+                            tool_window.request_redraw();
+                        }
+                    }
+                }
+                return;
+            }
+        }
+        // E.g. events still queued for a tool window that was just closed.
+        if self.window.as_ref().map(|window| window.id()) != Some(id) {
+            return;
+        }
+        let egui_repaint = state
             .egui_renderer
             .handle_input(self.window.as_ref().unwrap(), &event);
+        let gameplay_input = matches!(
+            event,
+            WindowEvent::KeyboardInput { .. }
+                | WindowEvent::MouseInput { .. }
+                | WindowEvent::Touch(_)
+                | WindowEvent::CursorMoved { .. }
+        );
+        if egui_repaint || gameplay_input {
+            self.wake();
+        }
+        let state = self.state.as_mut().unwrap();
 
         match event {
             WindowEvent::CloseRequested => {
+                state.save_settings(true);
                 event_loop.exit();
             }
             WindowEvent::RedrawRequested => {
-                self.handle_redraw();
+                self.handle_redraw(event_loop);
             }
             WindowEvent::Resized(new_size) => {
-                self.handle_resized(new_size.width, new_size.height);
+                self.handle_resized(event_loop, new_size.width, new_size.height);
+            }
+            WindowEvent::Occluded(occluded) => {
+                self.handle_occluded(event_loop, occluded);
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                state.modifiers = modifiers.state();
+            }
+            WindowEvent::Touch(touch)
+                if touch.phase != TouchPhase::Started || !state.wants_pointer_input() =>
+            {
+                let width = state.surface_config.width as f32;
+                state.touch_controls.handle_touch(&touch, width);
             }
             WindowEvent::MouseInput {
                 state: ElementState::Pressed,
                 button: MouseButton::Left,
                 ..
-            } => {
-                if !state.cursor_locked {
-                    state.cursor_locked = true;
-                    self.window
-                        .as_ref()
-                        .unwrap()
-                        .set_cursor_grab(winit::window::CursorGrabMode::Locked)
-                        .unwrap_or_else(|_| {
-                            self.window
-                                .as_ref()
-                                .unwrap()
-                                .set_cursor_grab(winit::window::CursorGrabMode::Confined)
-                                .unwrap();
-                        });
-                    self.window.as_ref().unwrap().set_cursor_visible(false);
+            } if state.settings.mouse.lock_on_click
+                && !state.cursor_locked
+                && !state.wants_pointer_input() =>
+            {
+                state.set_cursor_locked(true);
+            }
+            WindowEvent::MouseInput {
+                state: button_state,
+                button: MouseButton::Right,
+                ..
+            } if !state.settings.mouse.lock_on_click => match button_state {
+                ElementState::Pressed if !state.wants_pointer_input() => {
+                    state.set_cursor_locked(true);
+                }
+                ElementState::Pressed => {}
+                ElementState::Released => state.set_cursor_locked(false),
+            },
+            WindowEvent::CursorMoved { position, .. }
+                if state.cursor_locked && !state.uses_raw_motion() =>
+            {
+                if let Some(last) = state.last_cursor_position {
+                    state.mouse_delta.0 += (position.x - last.x) as f32;
+                    state.mouse_delta.1 += (position.y - last.y) as f32;
+                }
+                let center = PhysicalPosition::new(
+                    state.surface_config.width as f64 / 2.0,
+                    state.surface_config.height as f64 / 2.0,
+                );
+                state.last_cursor_position = Some(position);
+                if position != center {
+                    match state.window.set_cursor_position(center) {
+                        Ok(()) => state.last_cursor_position = Some(center),
+                        Err(err) => {
+                            tracing::info!(
+                                "Can't recentre the cursor ({err}), using raw mouse motion"
+                            );
+                            state.cursor_warp_unsupported = true;
+                        }
+                    }
                 }
             }
             WindowEvent::KeyboardInput {
@@ -294,29 +1981,33 @@ impl ApplicationHandler for App {
                     winit::event::KeyEvent {
                         physical_key: winit::keyboard::PhysicalKey::Code(keycode),
                         state: key_state,
+                        repeat,
                         ..
                     },
                 ..
             } => {
-                if keycode == winit::keyboard::KeyCode::Escape && key_state == ElementState::Pressed
+                if key_state == ElementState::Pressed
+                    && !repeat
+                    && !state.egui_renderer.context().wants_keyboard_input()
                 {
-                    if state.cursor_locked {
-                        state.cursor_locked = false;
-                        self.window
-                            .as_ref()
-                            .unwrap()
-                            .set_cursor_grab(winit::window::CursorGrabMode::None)
-                            .unwrap();
-                        self.window.as_ref().unwrap().set_cursor_visible(true);
+                    let action = state.settings.keybinds.action_for(
+                        keycode,
+                        state.modifiers,
+                        state.input_context(),
+                    );
+                    if let Some(action) = action {
+                        state.run_action(action);
+                        return;
                     }
                 }
 
                 match key_state {
-                    ElementState::Pressed => {
+                    ElementState::Pressed if !state.wants_keyboard_input() => {
                         if !state.pressed_keys.contains(&keycode) {
                             state.pressed_keys.push(keycode);
                         }
                     }
+                    ElementState::Pressed => {}
                     ElementState::Released => {
                         state.pressed_keys.retain(|&k| k != keycode);
                     }
@@ -333,14 +2024,89 @@ impl ApplicationHandler for App {
         event: DeviceEvent,
     ) {
         if let Some(state) = self.state.as_mut() {
-            match event {
-                DeviceEvent::MouseMotion { delta } => {
-                    if state.cursor_locked {
-                        state.mouse_delta = (delta.0 as f32, delta.1 as f32);
-                    }
+            if let DeviceEvent::MouseMotion { delta } = event {
+                if state.cursor_locked && state.uses_raw_motion() {
+                    state.mouse_delta.0 += delta.0 as f32;
+                    state.mouse_delta.1 += delta.1 as f32;
+                    self.wake();
                 }
-                _ => (),
             }
         }
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use wgpu::TextureFormat;
+
+    #[test]
+    fn surface_format_prefers_srgb() {
+        assert_eq!(
+            select_surface_format(&[
+                TextureFormat::Bgra8Unorm,
+                TextureFormat::Rgba8UnormSrgb,
+                TextureFormat::Bgra8UnormSrgb,
+            ]),
+            TextureFormat::Bgra8UnormSrgb
+        );
+        assert_eq!(
+            select_surface_format(&[TextureFormat::Rgba8Unorm, TextureFormat::Rgba8UnormSrgb]),
+            TextureFormat::Rgba8UnormSrgb
+        );
+    }
+
+    #[test]
+    fn surface_format_falls_back_to_8_bit_unorm() {
+        // Browsers usually only offer UNORM formats.
+        assert_eq!(
+            select_surface_format(&[TextureFormat::Rgba16Float, TextureFormat::Bgra8Unorm]),
+            TextureFormat::Bgra8Unorm
+        );
+        assert_eq!(
+            select_surface_format(&[TextureFormat::Rgb10a2Unorm, TextureFormat::Rgba8Unorm]),
+            TextureFormat::Rgba8Unorm
+        );
+    }
+
+    #[test]
+    fn surface_format_takes_srgb_before_the_first_one() {
+        assert_eq!(
+            select_surface_format(&[TextureFormat::Rgba16Float, TextureFormat::Rgba8UnormSrgb]),
+            TextureFormat::Rgba8UnormSrgb
+        );
+        assert_eq!(
+            select_surface_format(&[TextureFormat::Rgba16Float, TextureFormat::Rgb10a2Unorm]),
+            TextureFormat::Rgba16Float
+        );
+    }
+
+    #[test]
+    fn surface_format_never_takes_a_compressed_format() {
+        assert_eq!(
+            select_surface_format(&[TextureFormat::Bc1RgbaUnormSrgb, TextureFormat::Rgb10a2Unorm]),
+            TextureFormat::Rgb10a2Unorm
+        );
+    }
+
+    #[test]
+    fn command_paths_stay_inside_their_directory() {
+        assert_eq!(
+            path_in("scenes", "demo.ron"),
+            Ok(PathBuf::from("scenes/demo.ron"))
+        );
+        assert_eq!(
+            path_in("scenes", "tests/a.ron"),
+            Ok(PathBuf::from("scenes/tests/a.ron"))
+        );
+        for name in [
+            "",
+            "/etc/passwd",
+            "../a.ron",
+            "tests/../../a.ron",
+            "./a.ron",
+        ] {
+            assert!(path_in("scenes", name).is_err(), "{name} was accepted");
+        }
+    }
+}
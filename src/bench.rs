@@ -0,0 +1,135 @@
+use glam::Vec3;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::time::Duration;
+
+/// How long each benchmark flies its camera path.
+const DURATION: Duration = Duration::from_secs(20);
+
+/// A fixed camera path for `--bench`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BenchScene {
+    /// Circles the particle emitter while looking at it.
+    Orbit,
+    /// Flies straight through the emitter and out the other side.
+    Flythrough,
+}
+
+impl FromStr for BenchScene {
+    type Err = String;
+
+    fn from_str(name: &str) -> Result<Self, Self::Err> {
+        match name {
+            "orbit" => Ok(BenchScene::Orbit),
+            "flythrough" => Ok(BenchScene::Flythrough),
+            _ => Err(format!(
+                "Unknown benchmark scene `{name}`, expected `orbit` or `flythrough`"
+            )),
+        }
+    }
+}
+
+impl BenchScene {
+    pub fn name(&self) -> &'static str {
+        match self {
+            BenchScene::Orbit => "orbit",
+            BenchScene::Flythrough => "flythrough",
+        }
+    }
+
+    /// Camera position and look-at target `t` of the way through the path.
+    fn pose(&self, t: f32) -> (Vec3, Vec3) {
+        let target = Vec3::new(0.0, 4.0, -15.0);
+        match self {
+            BenchScene::Orbit => {
+                let angle = t * std::f32::consts::TAU;
+                let offset = Vec3::new(angle.cos(), 0.3, angle.sin()) * 12.0;
+                (target + offset, target)
+            }
+            BenchScene::Flythrough => {
+                let position = Vec3::new(0.0, 4.0, 10.0).lerp(Vec3::new(0.0, 4.0, -40.0), t);
+                (position, position + Vec3::NEG_Z)
+            }
+        }
+    }
+}
+
+struct BenchSample {
+    time: Duration,
+    frame_ms: f32,
+    cpu_ms: f32,
+    gpu_ms: Option<f32>,
+}
+
+/// Flies a fixed camera path and records per-frame timings for comparing changes.
+pub struct Benchmark {
+    pub scene: BenchScene,
+    elapsed: Duration,
+    samples: Vec<BenchSample>,
+}
+
+impl Benchmark {
+    pub fn new(scene: BenchScene) -> Self {
+        Self {
+            scene,
+            elapsed: Duration::ZERO,
+            samples: Vec::new(),
+        }
+    }
+
+    /// Where the camera should be for the current frame, as position and target.
+    pub fn camera_pose(&self) -> (Vec3, Vec3) {
+        self.scene
+            .pose(self.elapsed.as_secs_f32() / DURATION.as_secs_f32())
+    }
+
+    pub fn record(&mut self, frame_time: Duration, cpu_time: Duration, gpu_time: Option<Duration>) {
+        self.elapsed += frame_time;
+        self.samples.push(BenchSample {
+            time: self.elapsed,
+            frame_ms: frame_time.as_secs_f32() * 1000.0,
+            cpu_ms: cpu_time.as_secs_f32() * 1000.0,
+            gpu_ms: gpu_time.map(|t| t.as_secs_f32() * 1000.0),
+        });
+    }
+
+    pub fn is_finished(&self) -> bool {
+        self.elapsed >= DURATION
+    }
+
+    pub fn report_path(&self) -> PathBuf {
+        PathBuf::from(format!("bench_{}.csv", self.scene.name()))
+    }
+
+    /// Writes one CSV row per frame. The first frame is skipped since it includes startup.
+    pub fn write_report(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        writeln!(writer, "frame,time_s,frame_ms,cpu_ms,gpu_ms")?;
+        for (index, sample) in self.samples.iter().enumerate().skip(1) {
+            let gpu = sample.gpu_ms.map_or(String::new(), |ms| format!("{ms:.4}"));
+            writeln!(
+                writer,
+                "{index},{:.4},{:.4},{:.4},{gpu}",
+                sample.time.as_secs_f64(),
+                sample.frame_ms,
+                sample.cpu_ms
+            )?;
+        }
+        writer.flush()
+    }
+
+    /// Average FPS and the average FPS of the slowest 1% of frames.
+    pub fn summary(&self) -> (f32, f32) {
+        let mut frame_times: Vec<f32> = self.samples.iter().skip(1).map(|s| s.frame_ms).collect();
+        if frame_times.is_empty() {
+            return (0.0, 0.0);
+        }
+        let average = frame_times.iter().sum::<f32>() / frame_times.len() as f32;
+        frame_times.sort_by(|a, b| b.total_cmp(a));
+        let worst = &frame_times[..frame_times.len().div_ceil(100)];
+        let worst_average = worst.iter().sum::<f32>() / worst.len() as f32;
+        (1000.0 / average, 1000.0 / worst_average)
+    }
+}
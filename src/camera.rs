@@ -1,5 +1,30 @@
-use glam::{Mat4, Vec3};
+use glam::{Mat4, Vec2, Vec3};
+use serde::{Deserialize, Serialize};
 
+/// Vertical field of view in degrees.
+const FOV_Y: f32 = 70.0;
+const Z_NEAR: f32 = 0.1;
+const Z_FAR: f32 = 1000.0;
+
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct CameraSettings {
+    /// Distance moved per simulation tick.
+    pub speed: f32,
+    /// Degrees turned per pixel of mouse movement.
+    pub sensitivity: f32,
+}
+
+impl Default for CameraSettings {
+    fn default() -> Self {
+        Self {
+            speed: 1.1,
+            sensitivity: 1.0,
+        }
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub struct Camera {
     position: Vec3,
     yaw: f32,
@@ -9,21 +34,30 @@ pub struct Camera {
 }
 
 impl Camera {
-    pub fn new() -> Self {
+    pub fn new(settings: &CameraSettings) -> Self {
         Self {
             position: Vec3::new(0.0, 5.0, 0.0),
             yaw: -90.0,
             pitch: 0.0,
-            speed: 1.1,
-            sensitivity: 1.0,
+            speed: settings.speed,
+            sensitivity: settings.sensitivity,
         }
     }
 
+    pub fn apply_settings(&mut self, settings: &CameraSettings) {
+        self.speed = settings.speed;
+        self.sensitivity = settings.sensitivity;
+    }
+
     pub fn get_view_matrix(&self) -> Mat4 {
         let direction = self.get_direction();
         Mat4::look_at_rh(self.position, self.position + direction, Vec3::Y)
     }
 
+    pub fn get_projection_matrix(&self, aspect: f32) -> Mat4 {
+        Mat4::perspective_rh(FOV_Y.to_radians(), aspect, Z_NEAR, Z_FAR)
+    }
+
     pub fn get_direction(&self) -> Vec3 {
         Vec3::new(
             self.yaw.to_radians().cos() * self.pitch.to_radians().cos(),
@@ -36,7 +70,7 @@ impl Camera {
     pub fn handle_mouse(&mut self, delta: &(f64, f64)) {
         self.yaw += delta.0 as f32 * self.sensitivity;
         self.pitch -= delta.1 as f32 * self.sensitivity;
-        self.pitch = self.pitch.clamp(-89.0, 89.0); 
+        self.pitch = self.pitch.clamp(-89.0, 89.0);
     }
 
     pub fn handle_input(&mut self, keys: &[winit::keyboard::KeyCode]) {
@@ -56,7 +90,41 @@ impl Camera {
         }
     }
 
+    /// Analog movement where `x` strafes right and `y` moves forward, both in `[-1, 1]`.
+    pub fn handle_analog(&mut self, movement: Vec2) {
+        let direction = self.get_direction();
+        let right = direction.cross(Vec3::Y).normalize();
+
+        self.position += direction * movement.y * self.speed;
+        self.position += right * movement.x * self.speed;
+    }
+
     pub fn get_position(&self) -> Vec3 {
         self.position
     }
+
+    pub fn set_position(&mut self, position: Vec3) {
+        self.position = position;
+    }
+
+    /// The pose `t` of the way from `self` to `next`, turning the short way round.
+    pub fn interpolate(&self, next: &Camera, t: f32) -> Camera {
+        let yaw_delta = (next.yaw - self.yaw + 180.0).rem_euclid(360.0) - 180.0;
+        Camera {
+            position: self.position.lerp(next.position, t),
+            yaw: self.yaw + yaw_delta * t,
+            pitch: self.pitch + (next.pitch - self.pitch) * t,
+            ..next.clone()
+        }
+    }
+
+    /// Turns the camera to face `target`.
+    pub fn look_at(&mut self, target: Vec3) {
+        let direction = (target - self.position).normalize_or_zero();
+        if direction == Vec3::ZERO {
+            return;
+        }
+        self.yaw = direction.z.atan2(direction.x).to_degrees();
+        self.pitch = direction.y.asin().to_degrees().clamp(-89.0, 89.0);
+    }
 }
@@ -0,0 +1,329 @@
+/// A console command. `run` gets the arguments after the name and returns the
+/// text to print, or an error message.
+pub struct Command<C> {
+    pub name: &'static str,
+    pub usage: &'static str,
+    pub help: &'static str,
+    pub run: fn(&mut C, &[&str]) -> Result<String, String>,
+}
+
+/// Runs `line` against `commands`. `help` is built in and lists all of them.
+pub fn execute<C>(context: &mut C, commands: &[Command<C>], line: &str) -> Result<String, String> {
+    let mut words = line.split_whitespace();
+    let Some(name) = words.next() else {
+        return Ok(String::new());
+    };
+    let args: Vec<&str> = words.collect();
+
+    if name == "help" {
+        let lines: Vec<String> = commands
+            .iter()
+            .map(|command| format!("{:<24} {}", command.usage, command.help))
+            .collect();
+        return Ok(lines.join("\n"));
+    }
+    match commands.iter().find(|command| command.name == name) {
+        Some(command) => (command.run)(context, &args),
+        None => Err(format!("Unknown command `{name}`, try `help`")),
+    }
+}
+
+/// A drop-down developer console with history and completion of command names.
+pub struct Console {
+    pub open: bool,
+    input: String,
+    output: Vec<String>,
+    history: Vec<String>,
+    /// Position in `history` while browsing it with the arrow keys.
+    history_index: Option<usize>,
+    /// Focuses the input on the first frame after opening.
+    focus_input: bool,
+}
+
+impl Default for Console {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Console {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            input: String::new(),
+            output: Vec::new(),
+            history: Vec::new(),
+            history_index: None,
+            focus_input: false,
+        }
+    }
+
+    pub fn toggle(&mut self) {
+        self.open = !self.open;
+        self.focus_input = self.open;
+    }
+
+    /// Adds `text` to the console output and the log.
+    pub fn print(&mut self, text: &str) {
+        for line in text.lines() {
+            tracing::info!(target: "console", "{line}");
+            self.output.push(line.to_owned());
+        }
+    }
+
+    /// Draws the console and returns a line once it has been submitted.
+    pub fn ui<C>(&mut self, ctx: &egui::Context, commands: &[Command<C>]) -> Option<String> {
+        if !self.open {
+            return None;
+        }
+
+        let mut submitted = None;
+        egui::Window::new("Console")
+            .anchor(egui::Align2::CENTER_TOP, egui::Vec2::ZERO)
+            .default_width(600.0)
+            .collapsible(false)
+            .show(ctx, |ui| {
+                egui::ScrollArea::vertical()
+                    .max_height(240.0)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for line in &self.output {
+                            ui.monospace(line);
+                        }
+                    });
+                ui.separator();
+
+                // Tab completes instead of moving focus, the arrow keys browse the history.
+                let (tab, up, down) = ui.input_mut(|input| {
+                    (
+                        input.consume_key(egui::Modifiers::NONE, egui::Key::Tab),
+                        input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowUp),
+                        input.consume_key(egui::Modifiers::NONE, egui::Key::ArrowDown),
+                    )
+                });
+                if tab {
+                    self.complete(commands);
+                }
+                if up || down {
+                    self.browse_history(up);
+                }
+
+                let response = ui.add(
+                    egui::TextEdit::singleline(&mut self.input)
+                        .font(egui::TextStyle::Monospace)
+                        .desired_width(f32::INFINITY),
+                );
+                if std::mem::take(&mut self.focus_input) || tab || up || down {
+                    response.request_focus();
+                }
+
+                // The toggle key is typed into the input while it has focus.
+                if self.input.contains('`') {
+                    self.input.retain(|c| c != '`');
+                    self.open = false;
+                }
+                if response.lost_focus() && ui.input(|input| input.key_pressed(egui::Key::Enter)) {
+                    let line = std::mem::take(&mut self.input);
+                    if !line.trim().is_empty() {
+                        self.output.push(format!("> {line}"));
+                        self.history.push(line.clone());
+                        submitted = Some(line);
+                    }
+                    self.history_index = None;
+                    response.request_focus();
+                }
+
+                let prefix = self.input.trim_start();
+                if !prefix.is_empty() && !prefix.contains(' ') {
+                    for command in commands.iter().filter(|c| c.name.starts_with(prefix)) {
+                        ui.weak(format!("{} - {}", command.usage, command.help));
+                    }
+                }
+            });
+        submitted
+    }
+
+    /// Completes the command name to the longest prefix shared by all matches.
+    fn complete<C>(&mut self, commands: &[Command<C>]) {
+        let prefix = self.input.trim_start();
+        if prefix.contains(' ') {
+            return;
+        }
+        let matches: Vec<&str> = commands
+            .iter()
+            .map(|c| c.name)
+            .chain(["help"])
+            .filter(|name| name.starts_with(prefix))
+            .collect();
+        let Some((&first, others)) = matches.split_first() else {
+            return;
+        };
+        let common = others.iter().fold(first, |common, name| {
+            let len = common
+                .chars()
+                .zip(name.chars())
+                .take_while(|(a, b)| a == b)
+                .count();
+            &common[..len]
+        });
+        self.input = if others.is_empty() {
+            format!("{common} ")
+        } else {
+            common.to_owned()
+        };
+    }
+
+    fn browse_history(&mut self, older: bool) {
+        if self.history.is_empty() {
+            return;
+        }
+        let last = self.history.len() - 1;
+        self.history_index = match (self.history_index, older) {
+            (None, true) => Some(last),
+            (None, false) => None,
+            (Some(index), true) => Some(index.saturating_sub(1)),
+            (Some(index), false) if index < last => Some(index + 1),
+            (Some(_), false) => None,
+        };
+        self.input = self
+            .history_index
+            .map_or(String::new(), |index| self.history[index].clone());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Context {
+        value: i32,
+    }
+
+    const COMMANDS: &[Command<Context>] = &[
+        Command {
+            name: "set",
+            usage: "set <value>",
+            help: "Sets the value",
+            run: |context, args| {
+                let [value] = args else {
+                    return Err("Usage: set <value>".to_owned());
+                };
+                context.value = value
+                    .parse()
+                    .map_err(|_| format!("Not a number: {value}"))?;
+                Ok(format!("value = {}", context.value))
+            },
+        },
+        Command {
+            name: "show",
+            usage: "show",
+            help: "Prints the value",
+            run: |context, _| Ok(context.value.to_string()),
+        },
+        Command {
+            name: "shows",
+            usage: "shows",
+            help: "Prints the value again",
+            run: |context, _| Ok(context.value.to_string()),
+        },
+    ];
+
+    fn console_with_input(input: &str) -> Console {
+        let mut console = Console::new();
+        console.input = input.to_owned();
+        console
+    }
+
+    #[test]
+    fn execute_runs_the_named_command_with_its_arguments() {
+        let mut context = Context::default();
+        assert_eq!(
+            execute(&mut context, COMMANDS, "  set   42 "),
+            Ok("value = 42".to_owned())
+        );
+        assert_eq!(context.value, 42);
+        assert_eq!(execute(&mut context, COMMANDS, "show"), Ok("42".to_owned()));
+    }
+
+    #[test]
+    fn execute_reports_errors() {
+        let mut context = Context::default();
+        assert_eq!(
+            execute(&mut context, COMMANDS, "set x"),
+            Err("Not a number: x".to_owned())
+        );
+        assert_eq!(
+            execute(&mut context, COMMANDS, "set"),
+            Err("Usage: set <value>".to_owned())
+        );
+        assert!(execute(&mut context, COMMANDS, "unset")
+            .unwrap_err()
+            .contains("Unknown command `unset`"));
+        assert_eq!(context.value, 0);
+    }
+
+    #[test]
+    fn execute_ignores_blank_lines_and_lists_commands_on_help() {
+        let mut context = Context::default();
+        assert_eq!(execute(&mut context, COMMANDS, "   "), Ok(String::new()));
+
+        let help = execute(&mut context, COMMANDS, "help").unwrap();
+        assert_eq!(help.lines().count(), COMMANDS.len());
+        assert!(help.contains("Sets the value"));
+    }
+
+    #[test]
+    fn completion_finishes_a_unique_name() {
+        let mut console = console_with_input("se");
+        console.complete(COMMANDS);
+        assert_eq!(console.input, "set ");
+
+        let mut console = console_with_input("he");
+        console.complete(COMMANDS);
+        assert_eq!(console.input, "help ");
+    }
+
+    #[test]
+    fn completion_stops_at_the_common_prefix() {
+        let mut console = console_with_input("s");
+        console.complete(COMMANDS);
+        assert_eq!(console.input, "s");
+
+        let mut console = console_with_input("sh");
+        console.complete(COMMANDS);
+        assert_eq!(console.input, "show");
+    }
+
+    #[test]
+    fn completion_leaves_unknown_names_and_arguments_alone() {
+        let mut console = console_with_input("x");
+        console.complete(COMMANDS);
+        assert_eq!(console.input, "x");
+
+        let mut console = console_with_input("set 4");
+        console.complete(COMMANDS);
+        assert_eq!(console.input, "set 4");
+    }
+
+    #[test]
+    fn history_is_browsed_from_newest_to_oldest_and_back() {
+        let mut console = Console::new();
+        console.browse_history(true);
+        assert_eq!(console.input, "");
+
+        console.history = vec!["first".to_owned(), "second".to_owned()];
+        console.browse_history(true);
+        assert_eq!(console.input, "second");
+        console.browse_history(true);
+        assert_eq!(console.input, "first");
+        console.browse_history(true);
+        assert_eq!(console.input, "first");
+
+        console.browse_history(false);
+        assert_eq!(console.input, "second");
+        console.browse_history(false);
+        assert_eq!(console.input, "");
+        assert_eq!(console.history_index, None);
+    }
+}
@@ -1,7 +1,8 @@
-use egui::Context;
+use egui::{Context, ViewportId};
 use egui_wgpu::wgpu::{CommandEncoder, Device, Queue, StoreOp, TextureFormat, TextureView};
 use egui_wgpu::{wgpu, Renderer, ScreenDescriptor};
 use egui_winit::State;
+use std::time::Duration;
 use winit::event::WindowEvent;
 use winit::window::Window;
 
@@ -48,8 +49,9 @@ impl EguiRenderer {
         }
     }
 
-    pub fn handle_input(&mut self, window: &Window, event: &WindowEvent) {
-        let _ = self.state.on_window_event(window, event);
+    /// Returns whether egui needs a repaint in response to the event.
+    pub fn handle_input(&mut self, window: &Window, event: &WindowEvent) -> bool {
+        self.state.on_window_event(window, event).repaint
     }
 
     pub fn ppp(&mut self, v: f32) {
@@ -62,6 +64,7 @@ impl EguiRenderer {
         self.frame_started = true;
     }
 
+    /// Returns how long egui can wait before it needs to be repainted.
     pub fn end_frame_and_draw(
         &mut self,
         device: &Device,
@@ -70,7 +73,8 @@ impl EguiRenderer {
         window: &Window,
         window_surface_view: &TextureView,
         screen_descriptor: ScreenDescriptor,
-    ) {
+    ) -> Duration {
+        puffin::profile_function!();
         if !self.frame_started {
             panic!("begin_frame must be called before end_frame_and_draw can be called!");
         }
@@ -114,5 +118,10 @@ impl EguiRenderer {
         }
 
         self.frame_started = false;
+
+        full_output
+            .viewport_output
+            .get(&ViewportId::ROOT)
+            .map_or(Duration::MAX, |viewport| viewport.repaint_delay)
     }
 }
@@ -0,0 +1,108 @@
+use crate::scene_file::EntityDescription;
+use bevy_ecs::prelude::*;
+use glam::{Quat, Vec3};
+use serde::{Deserialize, Serialize};
+
+#[derive(Component, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Transform {
+    pub position: Vec3,
+    pub rotation: Quat,
+    pub scale: Vec3,
+}
+
+impl Transform {
+    pub fn from_position(position: Vec3) -> Self {
+        Self {
+            position,
+            rotation: Quat::IDENTITY,
+            scale: Vec3::ONE,
+        }
+    }
+}
+
+/// Linear velocity in units per second, applied every fixed tick.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Velocity(pub Vec3);
+
+/// Marks an entity for drawing. The template has no mesh renderer, so these are
+/// drawn as discs by [`EntitySprites`](crate::entity_sprites::EntitySprites);
+/// plugins can draw their own shapes instead.
+#[derive(Component, Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Renderable {
+    pub color: [f32; 4],
+}
+
+/// The dynamic entity layer: mobs, projectiles and props live here, separate
+/// from the static scene.
+pub struct Entities {
+    world: World,
+}
+
+impl Default for Entities {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Entities {
+    pub fn new() -> Self {
+        Self {
+            world: World::new(),
+        }
+    }
+
+    pub fn world(&self) -> &World {
+        &self.world
+    }
+
+    pub fn world_mut(&mut self) -> &mut World {
+        &mut self.world
+    }
+
+    pub fn spawn(&mut self, bundle: impl Bundle) -> Entity {
+        self.world.spawn(bundle).id()
+    }
+
+    /// Spawns an entity with the components of a scene file entry.
+    pub fn spawn_description(&mut self, description: &EntityDescription) -> Entity {
+        let mut entity = self.world.spawn(description.transform);
+        if let Some(velocity) = description.velocity {
+            entity.insert(velocity);
+        }
+        if let Some(renderable) = description.renderable {
+            entity.insert(renderable);
+        }
+        entity.id()
+    }
+
+    /// Despawns every entity.
+    pub fn clear(&mut self) {
+        self.world.clear_entities();
+    }
+
+    pub fn len(&self) -> u32 {
+        self.world.entities().len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+
+    /// Whether any entity has a non-zero velocity, so [`Entities::update`] moves it.
+    pub fn has_motion(&self) -> bool {
+        let Some(mut velocities) = self.world.try_query::<&Velocity>() else {
+            return false;
+        };
+        velocities
+            .iter(&self.world)
+            .any(|velocity| velocity.0 != Vec3::ZERO)
+    }
+
+    /// Advances all entities by one fixed tick of `dt` seconds.
+    pub fn update(&mut self, dt: f32) {
+        let mut moving = self.world.query::<(&mut Transform, &Velocity)>();
+        for (mut transform, velocity) in moving.iter_mut(&mut self.world) {
+            transform.position += velocity.0 * dt;
+        }
+    }
+}
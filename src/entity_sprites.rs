@@ -0,0 +1,210 @@
+use crate::camera::Camera;
+use crate::entities::{Entities, Renderable, Transform};
+use egui_wgpu::wgpu;
+use glam::Vec3;
+use std::collections::HashMap;
+
+/// Sprites allocated up front; the buffer doubles when a scene has more.
+const INITIAL_CAPACITY: u64 = 64;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct Sprite {
+    position: [f32; 3],
+    radius: f32,
+    color: [f32; 4],
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct CameraParams {
+    view_proj: [[f32; 4]; 4],
+    camera_right: [f32; 4],
+    camera_up: [f32; 4],
+}
+
+/// Draws every entity with a [`Renderable`] as a flat, camera-facing disc in
+/// its color, as wide as the largest axis of its scale.
+pub struct EntitySprites {
+    camera_params_buffer: wgpu::Buffer,
+    sprite_buffer: wgpu::Buffer,
+    bind_group: wgpu::BindGroup,
+    render_pipeline: wgpu::RenderPipeline,
+    /// Sprites written by the last [`EntitySprites::prepare`].
+    count: u32,
+}
+
+impl EntitySprites {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("entity_sprites.wgsl"));
+
+        let camera_params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("entity sprite camera params"),
+            size: std::mem::size_of::<CameraParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let sprite_buffer = create_sprite_buffer(device, INITIAL_CAPACITY);
+
+        let layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("entity sprite layout"),
+            entries: &[wgpu::BindGroupLayoutEntry {
+                binding: 0,
+                visibility: wgpu::ShaderStages::VERTEX,
+                ty: wgpu::BindingType::Buffer {
+                    ty: wgpu::BufferBindingType::Uniform,
+                    has_dynamic_offset: false,
+                    min_binding_size: None,
+                },
+                count: None,
+            }],
+        });
+        let bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("entity sprite bind group"),
+            layout: &layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 0,
+                resource: camera_params_buffer.as_entire_binding(),
+            }],
+        });
+
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("entity sprite render"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("entity sprite pipeline layout"),
+                    bind_group_layouts: &[&layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                // Center and radius, then color.
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: std::mem::size_of::<Sprite>() as u64,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x4, 1 => Float32x4],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants: &HashMap::from([(
+                        "SRGB_TARGET".to_owned(),
+                        if format.is_srgb() { 1.0 } else { 0.0 },
+                    )]),
+                    ..Default::default()
+                },
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            camera_params_buffer,
+            sprite_buffer,
+            bind_group,
+            render_pipeline,
+            count: 0,
+        }
+    }
+
+    pub fn buffer_bytes(&self) -> u64 {
+        self.camera_params_buffer.size() + self.sprite_buffer.size()
+    }
+
+    /// Uploads the renderable entities and the camera the next
+    /// [`EntitySprites::draw`] is seen from. Sprites are drawn in query order
+    /// without depth sorting.
+    pub fn prepare(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        entities: &Entities,
+        camera: &Camera,
+        aspect: f32,
+    ) {
+        let sprites: Vec<Sprite> = entities
+            .world()
+            .try_query::<(&Transform, &Renderable)>()
+            .map(|mut query| {
+                query
+                    .iter(entities.world())
+                    .map(|(transform, renderable)| Sprite {
+                        position: transform.position.into(),
+                        radius: transform.scale.max_element() * 0.5,
+                        color: renderable.color,
+                    })
+                    .collect()
+            })
+            .unwrap_or_default();
+        self.count = sprites.len() as u32;
+        if sprites.is_empty() {
+            return;
+        }
+
+        let needed = std::mem::size_of_val(sprites.as_slice()) as u64;
+        if needed > self.sprite_buffer.size() {
+            let capacity = (sprites.len() as u64).next_power_of_two();
+            self.sprite_buffer = create_sprite_buffer(device, capacity);
+        }
+        queue.write_buffer(&self.sprite_buffer, 0, bytemuck::cast_slice(&sprites));
+
+        let direction = camera.get_direction();
+        let right = direction.cross(Vec3::Y).normalize();
+        let up = right.cross(direction);
+        let params = CameraParams {
+            view_proj: (camera.get_projection_matrix(aspect) * camera.get_view_matrix())
+                .to_cols_array_2d(),
+            camera_right: right.extend(0.0).into(),
+            camera_up: up.extend(0.0).into(),
+        };
+        queue.write_buffer(&self.camera_params_buffer, 0, bytemuck::bytes_of(&params));
+    }
+
+    /// Draws the sprites over whatever is already in `view`.
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        if self.count == 0 {
+            return;
+        }
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("entity sprite pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(&self.render_pipeline);
+        pass.set_bind_group(0, &self.bind_group, &[]);
+        pass.set_vertex_buffer(0, self.sprite_buffer.slice(..));
+        pass.draw(0..6, 0..self.count);
+    }
+}
+
+fn create_sprite_buffer(device: &wgpu::Device, capacity: u64) -> wgpu::Buffer {
+    device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("entity sprites"),
+        size: capacity * std::mem::size_of::<Sprite>() as u64,
+        usage: wgpu::BufferUsages::VERTEX | wgpu::BufferUsages::COPY_DST,
+        mapped_at_creation: false,
+    })
+}
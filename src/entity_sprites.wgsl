@@ -0,0 +1,59 @@
+struct CameraParams {
+    view_proj: mat4x4<f32>,
+    camera_right: vec4<f32>,
+    camera_up: vec4<f32>,
+}
+
+@group(0) @binding(0) var<uniform> camera: CameraParams;
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) corner: vec2<f32>,
+    @location(1) color: vec4<f32>,
+}
+
+@vertex
+fn vs_main(
+    @builtin(vertex_index) vertex: u32,
+    @location(0) sprite: vec4<f32>,
+    @location(1) color: vec4<f32>,
+) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[vertex];
+    // xyz is the center, w the radius.
+    let offset = camera.camera_right.xyz * corner.x + camera.camera_up.xyz * corner.y;
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(sprite.xyz + offset * sprite.w, 1.0);
+    out.corner = corner;
+    out.color = color;
+    return out;
+}
+
+// Whether the render target encodes sRGB itself. Otherwise the shader does.
+override SRGB_TARGET: bool = true;
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    if length(in.corner) > 1.0 {
+        discard;
+    }
+    var color = in.color.rgb;
+    if !SRGB_TARGET {
+        color = linear_to_srgb(color);
+    }
+    return vec4<f32>(color, in.color.a);
+}
@@ -0,0 +1,85 @@
+use std::path::PathBuf;
+
+/// Top-level state of the app. The simulation only advances while `Playing`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum GameState {
+    MainMenu,
+    Playing,
+    Paused,
+}
+
+/// A button picked in the main menu or the pause overlay.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MenuChoice {
+    Play,
+    /// Load the scene file at this index and play it.
+    LoadScene(usize),
+    Resume,
+    Settings,
+    MainMenu,
+    Quit,
+}
+
+fn menu_window(
+    ctx: &egui::Context,
+    title: &str,
+    add_buttons: impl FnOnce(&mut egui::Ui, &mut Option<MenuChoice>),
+) -> Option<MenuChoice> {
+    let mut choice = None;
+    egui::Window::new(title)
+        .anchor(egui::Align2::CENTER_CENTER, egui::Vec2::ZERO)
+        .collapsible(false)
+        .resizable(false)
+        .show(ctx, |ui| {
+            ui.vertical_centered_justified(|ui| add_buttons(ui, &mut choice));
+        });
+    choice
+}
+
+/// Lists `scenes` below the Play button so they can be loaded directly.
+pub fn main_menu(ctx: &egui::Context, scenes: &[PathBuf]) -> Option<MenuChoice> {
+    menu_window(ctx, "Main Menu", |ui, choice| {
+        if ui.button("Play").clicked() {
+            *choice = Some(MenuChoice::Play);
+        }
+        if !scenes.is_empty() {
+            ui.separator();
+            ui.label("Scenes");
+            for (index, path) in scenes.iter().enumerate() {
+                let name = path.file_stem().unwrap_or_default().to_string_lossy();
+                if ui.button(name).clicked() {
+                    *choice = Some(MenuChoice::LoadScene(index));
+                }
+            }
+            ui.separator();
+        }
+        if ui.button("Settings").clicked() {
+            *choice = Some(MenuChoice::Settings);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if ui.button("Quit").clicked() {
+            *choice = Some(MenuChoice::Quit);
+        }
+    })
+}
+
+/// Dims the frame behind the pause menu.
+pub fn pause_overlay(ctx: &egui::Context) -> Option<MenuChoice> {
+    ctx.layer_painter(egui::LayerId::background()).rect_filled(
+        ctx.screen_rect(),
+        0.0,
+        egui::Color32::from_black_alpha(160),
+    );
+    menu_window(ctx, "Paused", |ui, choice| {
+        if ui.button("Resume").clicked() {
+            *choice = Some(MenuChoice::Resume);
+        }
+        if ui.button("Main menu").clicked() {
+            *choice = Some(MenuChoice::MainMenu);
+        }
+        #[cfg(not(target_arch = "wasm32"))]
+        if ui.button("Quit").clicked() {
+            *choice = Some(MenuChoice::Quit);
+        }
+    })
+}
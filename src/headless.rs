@@ -0,0 +1,257 @@
+use crate::app::FIXED_TIMESTEP;
+use crate::camera::{Camera, CameraSettings};
+use crate::entities::Entities;
+use crate::entity_sprites::EntitySprites;
+use crate::particles::ParticleSystem;
+use crate::scene;
+use crate::scene_file::SceneFile;
+use egui_wgpu::wgpu;
+use std::fmt;
+use std::fs::File;
+use std::io::{self, BufReader, BufWriter};
+use std::path::Path;
+use std::sync::mpsc;
+
+const FORMAT: wgpu::TextureFormat = wgpu::TextureFormat::Rgba8UnormSrgb;
+
+/// An 8-bit sRGB RGBA image read back from the GPU.
+pub struct RgbaImage {
+    pub width: u32,
+    pub height: u32,
+    pub pixels: Vec<u8>,
+}
+
+impl RgbaImage {
+    pub fn save_png(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let writer = BufWriter::new(File::create(path)?);
+        let mut encoder = png::Encoder::new(writer, self.width, self.height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.set_source_srgb(png::SrgbRenderingIntent::Perceptual);
+        let mut writer = encoder.write_header().map_err(io::Error::other)?;
+        writer
+            .write_image_data(&self.pixels)
+            .map_err(io::Error::other)
+    }
+
+    /// Loads an 8-bit RGBA PNG, such as one written by [`RgbaImage::save_png`].
+    pub fn load_png(path: impl AsRef<Path>) -> io::Result<Self> {
+        let decoder = png::Decoder::new(BufReader::new(File::open(path)?));
+        let mut reader = decoder.read_info().map_err(io::Error::other)?;
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).map_err(io::Error::other)?;
+        if info.color_type != png::ColorType::Rgba || info.bit_depth != png::BitDepth::Eight {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidData,
+                "expected an 8-bit RGBA image",
+            ));
+        }
+        pixels.truncate(info.buffer_size());
+
+        Ok(Self {
+            width: info.width,
+            height: info.height,
+            pixels,
+        })
+    }
+}
+
+#[derive(Debug)]
+pub enum HeadlessError {
+    /// There is no graphics adapter to render with.
+    NoAdapter,
+    RequestDevice(wgpu::RequestDeviceError),
+}
+
+impl fmt::Display for HeadlessError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HeadlessError::NoAdapter => write!(f, "no graphics adapter available"),
+            HeadlessError::RequestDevice(err) => write!(f, "failed to create device: {err}"),
+        }
+    }
+}
+
+impl std::error::Error for HeadlessError {}
+
+/// Renders the scene without a window or surface, e.g. for automated renders
+/// and thumbnails on machines without a display.
+pub struct HeadlessRenderer {
+    device: wgpu::Device,
+    queue: wgpu::Queue,
+}
+
+impl HeadlessRenderer {
+    pub async fn new() -> Result<Self, HeadlessError> {
+        let instance = wgpu::Instance::new(&wgpu::InstanceDescriptor::default());
+        let adapter = instance
+            .request_adapter(&wgpu::RequestAdapterOptions {
+                power_preference: wgpu::PowerPreference::default(),
+                force_fallback_adapter: false,
+                compatible_surface: None,
+            })
+            .await
+            .ok_or(HeadlessError::NoAdapter)?;
+
+        let (device, queue) = adapter
+            .request_device(
+                &wgpu::DeviceDescriptor {
+                    label: Some("headless device"),
+                    required_features: wgpu::Features::empty(),
+                    required_limits: wgpu::Limits::default(),
+                    memory_hints: wgpu::MemoryHints::default(),
+                },
+                None,
+            )
+            .await
+            .map_err(HeadlessError::RequestDevice)?;
+
+        Ok(Self { device, queue })
+    }
+
+    /// Renders the background of an empty scene.
+    pub fn render(&self, width: u32, height: u32) -> Result<RgbaImage, wgpu::BufferAsyncError> {
+        let (texture, view) = self.create_target(width, height);
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("headless encoder"),
+            });
+        scene::draw(&mut encoder, &view, FORMAT);
+        read_texture(&self.device, &self.queue, encoder, &texture)
+    }
+
+    /// Renders `scene` as it looks after `ticks` fixed ticks. The particles and
+    /// entities only depend on the simulated time, so the image is the same on
+    /// every run.
+    pub fn render_scene(
+        &self,
+        scene: &SceneFile,
+        ticks: u32,
+        width: u32,
+        height: u32,
+    ) -> Result<RgbaImage, wgpu::BufferAsyncError> {
+        let mut camera = Camera::new(&CameraSettings::default());
+        camera.set_position(scene.camera.position);
+        camera.look_at(scene.camera.target);
+
+        let mut particles = ParticleSystem::new(&self.device, FORMAT);
+        particles.apply_settings(&scene.particles);
+        for _ in 0..ticks {
+            particles.step(&self.device, &self.queue, FIXED_TIMESTEP.as_secs_f32());
+        }
+        let mut entities = Entities::new();
+        for entity in &scene.entities {
+            entities.spawn_description(entity);
+        }
+        for _ in 0..ticks {
+            entities.update(FIXED_TIMESTEP.as_secs_f32());
+        }
+
+        let aspect = width as f32 / height as f32;
+        let mut entity_sprites = EntitySprites::new(&self.device, FORMAT);
+        entity_sprites.prepare(&self.device, &self.queue, &entities, &camera, aspect);
+        particles.prepare(&self.queue, &camera, aspect);
+
+        let (texture, view) = self.create_target(width, height);
+        let mut encoder = self
+            .device
+            .create_command_encoder(&wgpu::CommandEncoderDescriptor {
+                label: Some("headless encoder"),
+            });
+        scene::draw(&mut encoder, &view, FORMAT);
+        entity_sprites.draw(&mut encoder, &view);
+        particles.draw(&mut encoder, &view);
+        read_texture(&self.device, &self.queue, encoder, &texture)
+    }
+
+    fn create_target(&self, width: u32, height: u32) -> (wgpu::Texture, wgpu::TextureView) {
+        let texture = self.device.create_texture(&wgpu::TextureDescriptor {
+            label: Some("headless target"),
+            size: wgpu::Extent3d {
+                width,
+                height,
+                depth_or_array_layers: 1,
+            },
+            mip_level_count: 1,
+            sample_count: 1,
+            dimension: wgpu::TextureDimension::D2,
+            format: FORMAT,
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT | wgpu::TextureUsages::COPY_SRC,
+            view_formats: &[],
+        });
+        let view = texture.create_view(&wgpu::TextureViewDescriptor::default());
+        (texture, view)
+    }
+}
+
+/// Copies `texture` into an image after the commands in `encoder`, which usually
+/// render into it, and waits for the result. The texture must be 8-bit RGBA or
+/// BGRA and have `COPY_SRC` usage.
+pub fn read_texture(
+    device: &wgpu::Device,
+    queue: &wgpu::Queue,
+    mut encoder: wgpu::CommandEncoder,
+    texture: &wgpu::Texture,
+) -> Result<RgbaImage, wgpu::BufferAsyncError> {
+    let width = texture.width();
+    let height = texture.height();
+
+    // Rows in a texture-to-buffer copy must be padded to a fixed alignment.
+    let unpadded_bytes_per_row = width * 4;
+    let padded_bytes_per_row = unpadded_bytes_per_row.div_ceil(wgpu::COPY_BYTES_PER_ROW_ALIGNMENT)
+        * wgpu::COPY_BYTES_PER_ROW_ALIGNMENT;
+    let readback = device.create_buffer(&wgpu::BufferDescriptor {
+        label: Some("texture readback"),
+        size: padded_bytes_per_row as u64 * height as u64,
+        usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+        mapped_at_creation: false,
+    });
+
+    encoder.copy_texture_to_buffer(
+        texture.as_image_copy(),
+        wgpu::TexelCopyBufferInfo {
+            buffer: &readback,
+            layout: wgpu::TexelCopyBufferLayout {
+                offset: 0,
+                bytes_per_row: Some(padded_bytes_per_row),
+                rows_per_image: Some(height),
+            },
+        },
+        texture.size(),
+    );
+    queue.submit(Some(encoder.finish()));
+
+    let slice = readback.slice(..);
+    let (sender, mapped) = mpsc::channel();
+    slice.map_async(wgpu::MapMode::Read, move |result| {
+        let _ = sender.send(result);
+    });
+    device.poll(wgpu::Maintain::Wait);
+    // The callback is dropped without being called if the device was lost.
+    mapped.recv().unwrap_or(Err(wgpu::BufferAsyncError))?;
+
+    let mut pixels = Vec::with_capacity((unpadded_bytes_per_row * height) as usize);
+    {
+        let data = slice.get_mapped_range();
+        for row in data.chunks(padded_bytes_per_row as usize) {
+            pixels.extend_from_slice(&row[..unpadded_bytes_per_row as usize]);
+        }
+    }
+    readback.unmap();
+
+    if matches!(
+        texture.format(),
+        wgpu::TextureFormat::Bgra8Unorm | wgpu::TextureFormat::Bgra8UnormSrgb
+    ) {
+        for pixel in pixels.chunks_exact_mut(4) {
+            pixel.swap(0, 2);
+        }
+    }
+
+    Ok(RgbaImage {
+        width,
+        height,
+        pixels,
+    })
+}
@@ -0,0 +1,337 @@
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use winit::keyboard::{KeyCode, ModifiersState};
+
+/// Something a hotkey can trigger.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum Action {
+    ReleaseCursor,
+    TogglePause,
+    ToggleUiMode,
+    ToggleUiVisible,
+    ToggleConsole,
+    ResetCamera,
+    TogglePhotoMode,
+    SavePhoto,
+}
+
+impl Action {
+    pub fn label(&self) -> &'static str {
+        match self {
+            Action::ReleaseCursor => "Release cursor",
+            Action::TogglePause => "Pause",
+            Action::ToggleUiMode => "Toggle UI mode",
+            Action::ToggleUiVisible => "Hide UI",
+            Action::ToggleConsole => "Toggle console",
+            Action::ResetCamera => "Reset camera",
+            Action::TogglePhotoMode => "Photo mode",
+            Action::SavePhoto => "Save photo",
+        }
+    }
+}
+
+/// Which part of the app currently owns the keyboard.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum InputContext {
+    /// The cursor is captured and the camera is flying.
+    Fly,
+    /// The cursor is free over the viewport.
+    Viewport,
+    /// UI mode, a menu or the pause overlay: gameplay input is suspended.
+    Menu,
+}
+
+/// A key pressed together with an exact set of modifiers.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub struct Chord {
+    pub key: KeyCode,
+    pub modifiers: ModifiersState,
+}
+
+impl Chord {
+    pub fn new(key: KeyCode) -> Self {
+        Self {
+            key,
+            modifiers: ModifiersState::empty(),
+        }
+    }
+
+    pub fn with(mut self, modifiers: ModifiersState) -> Self {
+        self.modifiers |= modifiers;
+        self
+    }
+}
+
+impl fmt::Display for Chord {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if self.modifiers.control_key() {
+            write!(f, "Ctrl+")?;
+        }
+        if self.modifiers.alt_key() {
+            write!(f, "Alt+")?;
+        }
+        if self.modifiers.shift_key() {
+            write!(f, "Shift+")?;
+        }
+        if self.modifiers.super_key() {
+            write!(f, "Super+")?;
+        }
+        write!(f, "{:?}", self.key)
+    }
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Binding {
+    pub chord: Chord,
+    /// `None` makes the binding active in every context.
+    pub context: Option<InputContext>,
+    pub action: Action,
+}
+
+/// Saved as the plain list of bindings. Loading a list adds the default
+/// bindings of any action it doesn't mention, so settings files written by an
+/// older version pick up newly added actions.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(from = "Vec<Binding>", into = "Vec<Binding>")]
+pub struct Hotkeys {
+    bindings: Vec<Binding>,
+}
+
+impl From<Vec<Binding>> for Hotkeys {
+    fn from(bindings: Vec<Binding>) -> Self {
+        let mut hotkeys = Self { bindings };
+        for default in Self::default().bindings {
+            let action_bound = hotkeys.bindings.iter().any(|b| b.action == default.action);
+            let chord_taken = hotkeys
+                .bindings
+                .iter()
+                .any(|b| b.chord == default.chord && b.context == default.context);
+            if !action_bound && !chord_taken {
+                hotkeys.bindings.push(default);
+            }
+        }
+        hotkeys
+    }
+}
+
+impl From<Hotkeys> for Vec<Binding> {
+    fn from(hotkeys: Hotkeys) -> Self {
+        hotkeys.bindings
+    }
+}
+
+impl Default for Hotkeys {
+    fn default() -> Self {
+        let mut hotkeys = Self {
+            bindings: Vec::new(),
+        };
+        hotkeys.bind(Chord::new(KeyCode::Escape), None, Action::TogglePause);
+        hotkeys.bind(
+            Chord::new(KeyCode::Escape),
+            Some(InputContext::Fly),
+            Action::ReleaseCursor,
+        );
+        hotkeys.bind(Chord::new(KeyCode::Tab), None, Action::ToggleUiMode);
+        hotkeys.bind(Chord::new(KeyCode::F1), None, Action::ToggleUiVisible);
+        hotkeys.bind(Chord::new(KeyCode::F2), None, Action::TogglePhotoMode);
+        hotkeys.bind(Chord::new(KeyCode::F12), None, Action::SavePhoto);
+        hotkeys.bind(Chord::new(KeyCode::Backquote), None, Action::ToggleConsole);
+        hotkeys.bind(
+            Chord::new(KeyCode::KeyR).with(ModifiersState::CONTROL),
+            None,
+            Action::ResetCamera,
+        );
+        hotkeys
+    }
+}
+
+impl Hotkeys {
+    /// Adds a binding. Context-specific bindings take precedence over global ones.
+    pub fn bind(&mut self, chord: Chord, context: Option<InputContext>, action: Action) {
+        self.bindings.push(Binding {
+            chord,
+            context,
+            action,
+        });
+    }
+
+    pub fn action_for(
+        &self,
+        key: KeyCode,
+        modifiers: ModifiersState,
+        context: InputContext,
+    ) -> Option<Action> {
+        let chord = Chord { key, modifiers };
+        let mut matching = self.bindings.iter().filter(|b| b.chord == chord);
+
+        matching
+            .clone()
+            .find(|b| b.context == Some(context))
+            .or_else(|| matching.find(|b| b.context.is_none()))
+            .map(|b| b.action)
+    }
+
+    pub fn bindings(&self) -> &[Binding] {
+        &self.bindings
+    }
+
+    /// Pairs of bindings for the same chord in the same context, of which only
+    /// the first can ever trigger.
+    pub fn conflicts(&self) -> Vec<(&Binding, &Binding)> {
+        let mut conflicts = Vec::new();
+        for (index, first) in self.bindings.iter().enumerate() {
+            for second in &self.bindings[index + 1..] {
+                if first.chord == second.chord && first.context == second.context {
+                    conflicts.push((first, second));
+                }
+            }
+        }
+        conflicts
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn context_binding_takes_precedence_over_global() {
+        let hotkeys = Hotkeys::default();
+        let none = ModifiersState::empty();
+
+        assert_eq!(
+            hotkeys.action_for(KeyCode::Escape, none, InputContext::Fly),
+            Some(Action::ReleaseCursor)
+        );
+        assert_eq!(
+            hotkeys.action_for(KeyCode::Escape, none, InputContext::Viewport),
+            Some(Action::TogglePause)
+        );
+        assert_eq!(
+            hotkeys.action_for(KeyCode::Escape, none, InputContext::Menu),
+            Some(Action::TogglePause)
+        );
+    }
+
+    #[test]
+    fn context_binding_precedence_does_not_depend_on_order() {
+        let mut hotkeys = Hotkeys {
+            bindings: Vec::new(),
+        };
+        hotkeys.bind(
+            Chord::new(KeyCode::KeyP),
+            Some(InputContext::Menu),
+            Action::SavePhoto,
+        );
+        hotkeys.bind(Chord::new(KeyCode::KeyP), None, Action::TogglePhotoMode);
+
+        let none = ModifiersState::empty();
+        assert_eq!(
+            hotkeys.action_for(KeyCode::KeyP, none, InputContext::Menu),
+            Some(Action::SavePhoto)
+        );
+        assert_eq!(
+            hotkeys.action_for(KeyCode::KeyP, none, InputContext::Fly),
+            Some(Action::TogglePhotoMode)
+        );
+    }
+
+    #[test]
+    fn chords_need_the_exact_modifiers() {
+        let hotkeys = Hotkeys::default();
+        let context = InputContext::Viewport;
+
+        assert_eq!(
+            hotkeys.action_for(KeyCode::KeyR, ModifiersState::CONTROL, context),
+            Some(Action::ResetCamera)
+        );
+        assert_eq!(
+            hotkeys.action_for(KeyCode::KeyR, ModifiersState::empty(), context),
+            None
+        );
+        assert_eq!(
+            hotkeys.action_for(
+                KeyCode::KeyR,
+                ModifiersState::CONTROL | ModifiersState::SHIFT,
+                context
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn defaults_have_no_conflicts_and_bind_every_action() {
+        let hotkeys = Hotkeys::default();
+        assert!(hotkeys.conflicts().is_empty());
+
+        for action in [
+            Action::ReleaseCursor,
+            Action::TogglePause,
+            Action::ToggleUiMode,
+            Action::ToggleUiVisible,
+            Action::ToggleConsole,
+            Action::ResetCamera,
+            Action::TogglePhotoMode,
+            Action::SavePhoto,
+        ] {
+            assert!(
+                hotkeys.bindings().iter().any(|b| b.action == action),
+                "{action:?} has no default binding"
+            );
+        }
+    }
+
+    #[test]
+    fn loading_adds_defaults_for_unbound_actions() {
+        let mut saved = Hotkeys {
+            bindings: Vec::new(),
+        };
+        saved.bind(Chord::new(KeyCode::KeyP), None, Action::TogglePause);
+        saved.bind(Chord::new(KeyCode::F1), None, Action::SavePhoto);
+        let loaded = Hotkeys::from(Vec::from(saved));
+
+        let none = ModifiersState::empty();
+        let context = InputContext::Viewport;
+        // Rebound actions keep only their new chord.
+        assert_eq!(
+            loaded.action_for(KeyCode::KeyP, none, context),
+            Some(Action::TogglePause)
+        );
+        assert_eq!(loaded.action_for(KeyCode::Escape, none, context), None);
+        // Missing actions get their defaults unless the chord is already taken.
+        assert_eq!(
+            loaded.action_for(KeyCode::Backquote, none, context),
+            Some(Action::ToggleConsole)
+        );
+        assert_eq!(
+            loaded.action_for(KeyCode::F1, none, context),
+            Some(Action::SavePhoto)
+        );
+        assert!(loaded
+            .bindings()
+            .iter()
+            .all(|b| b.action != Action::ToggleUiVisible));
+        assert!(loaded.conflicts().is_empty());
+    }
+
+    #[test]
+    fn same_chord_in_the_same_context_conflicts() {
+        let mut hotkeys = Hotkeys::default();
+        hotkeys.bind(Chord::new(KeyCode::F1), None, Action::SavePhoto);
+        hotkeys.bind(
+            Chord::new(KeyCode::Tab),
+            Some(InputContext::Menu),
+            Action::ToggleConsole,
+        );
+
+        let conflicts = hotkeys.conflicts();
+        assert_eq!(conflicts.len(), 1);
+        assert_eq!(conflicts[0].0.action, Action::ToggleUiVisible);
+        assert_eq!(conflicts[0].1.action, Action::SavePhoto);
+        // The earlier binding wins.
+        assert_eq!(
+            hotkeys.action_for(KeyCode::F1, ModifiersState::empty(), InputContext::Fly),
+            Some(Action::ToggleUiVisible)
+        );
+    }
+}
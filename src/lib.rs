@@ -0,0 +1,38 @@
+//! A wgpu renderer with an egui overlay and an FPS camera.
+//!
+//! The binary runs [`App`] on a winit event loop. To embed the renderer in your
+//! own application, create an [`AppState`] for your window and call
+//! [`AppState::update`] and [`AppState::render`] from your event loop.
+
+pub mod app;
+pub mod bench;
+pub mod camera;
+pub mod console;
+pub mod egui_tools;
+pub mod entities;
+pub mod entity_sprites;
+pub mod game_state;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod headless;
+pub mod hotkeys;
+pub mod logging;
+pub mod navigation;
+pub mod particles;
+pub mod perf;
+pub mod plugin;
+pub mod profiler;
+pub mod recording;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod remote;
+pub mod render_scale;
+pub mod scene;
+pub mod scene_file;
+pub mod settings;
+#[cfg(not(target_arch = "wasm32"))]
+pub mod tool_window;
+pub mod touch;
+
+pub use app::{App, AppState};
+pub use camera::{Camera, CameraSettings};
+pub use plugin::EnginePlugin;
+pub use settings::Settings;
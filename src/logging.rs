@@ -0,0 +1,145 @@
+use std::collections::VecDeque;
+use std::fmt::Write;
+use std::sync::Mutex;
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+use tracing_subscriber::filter::Targets;
+use tracing_subscriber::layer::{Context, SubscriberExt};
+use tracing_subscriber::util::SubscriberInitExt;
+use tracing_subscriber::Layer;
+
+/// How many records the log viewer keeps.
+const CAPACITY: usize = 2000;
+
+static RECORDS: Mutex<VecDeque<LogRecord>> = Mutex::new(VecDeque::new());
+
+#[derive(Clone)]
+pub struct LogRecord {
+    pub level: Level,
+    pub target: String,
+    pub message: String,
+}
+
+/// Keeps the latest events in memory for [`LogViewer`].
+pub struct RingBufferLayer;
+
+impl<S: Subscriber> Layer<S> for RingBufferLayer {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut visitor = MessageVisitor(String::new());
+        event.record(&mut visitor);
+
+        let mut records = RECORDS.lock().unwrap_or_else(|err| err.into_inner());
+        if records.len() == CAPACITY {
+            records.pop_front();
+        }
+        records.push_back(LogRecord {
+            level: *event.metadata().level(),
+            target: event.metadata().target().to_owned(),
+            message: visitor.0,
+        });
+    }
+}
+
+/// Formats the message followed by any other fields as `name=value`.
+struct MessageVisitor(String);
+
+impl Visit for MessageVisitor {
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{value:?}");
+        } else {
+            let _ = write!(self.0, " {}={value:?}", field.name());
+        }
+    }
+}
+
+/// Installs the global subscriber: the in-memory log plus stderr on native
+/// targets. Dependencies only log from `INFO` up so wgpu doesn't flood the log.
+pub fn init() {
+    let filter = Targets::new()
+        .with_default(Level::INFO)
+        .with_target(env!("CARGO_CRATE_NAME"), Level::TRACE);
+    let registry = tracing_subscriber::registry()
+        .with(filter)
+        .with(RingBufferLayer);
+    #[cfg(not(target_arch = "wasm32"))]
+    let registry = registry.with(tracing_subscriber::fmt::layer().with_writer(std::io::stderr));
+    registry.init();
+}
+
+/// An egui window listing recent log records, filtered by level and target.
+pub struct LogViewer {
+    pub open: bool,
+    max_level: Level,
+    target_filter: String,
+}
+
+impl Default for LogViewer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl LogViewer {
+    pub fn new() -> Self {
+        Self {
+            open: false,
+            max_level: Level::INFO,
+            target_filter: String::new(),
+        }
+    }
+
+    pub fn ui(&mut self, ctx: &egui::Context) {
+        let mut open = self.open;
+        egui::Window::new("Log")
+            .open(&mut open)
+            .default_size([600.0, 300.0])
+            .show(ctx, |ui| {
+                ui.horizontal(|ui| {
+                    egui::ComboBox::from_id_salt("log_level")
+                        .selected_text(self.max_level.as_str())
+                        .show_ui(ui, |ui| {
+                            for level in [
+                                Level::ERROR,
+                                Level::WARN,
+                                Level::INFO,
+                                Level::DEBUG,
+                                Level::TRACE,
+                            ] {
+                                ui.selectable_value(&mut self.max_level, level, level.as_str());
+                            }
+                        });
+                    ui.label("Module");
+                    ui.text_edit_singleline(&mut self.target_filter);
+                });
+                ui.separator();
+
+                let records = RECORDS.lock().unwrap_or_else(|err| err.into_inner());
+                egui::ScrollArea::vertical()
+                    .auto_shrink(false)
+                    .stick_to_bottom(true)
+                    .show(ui, |ui| {
+                        for record in records.iter().filter(|r| {
+                            r.level <= self.max_level && r.target.contains(&self.target_filter)
+                        }) {
+                            ui.horizontal(|ui| {
+                                ui.colored_label(level_color(record.level), record.level.as_str());
+                                ui.weak(&record.target);
+                                ui.label(&record.message);
+                            });
+                        }
+                    });
+            });
+        self.open = open;
+    }
+}
+
+fn level_color(level: Level) -> egui::Color32 {
+    match level {
+        Level::ERROR => egui::Color32::LIGHT_RED,
+        Level::WARN => egui::Color32::YELLOW,
+        Level::INFO => egui::Color32::LIGHT_GREEN,
+        Level::DEBUG => egui::Color32::LIGHT_BLUE,
+        Level::TRACE => egui::Color32::GRAY,
+    }
+}
@@ -1,22 +1,130 @@
-mod app;
-mod camera;
-mod egui_tools;
-
+use egui_rust_wgpu_hello::bench::BenchScene;
+#[cfg(not(target_arch = "wasm32"))]
+use egui_rust_wgpu_hello::headless;
+#[cfg(not(target_arch = "wasm32"))]
+use egui_rust_wgpu_hello::remote::RemoteControl;
+#[cfg(not(target_arch = "wasm32"))]
+use egui_rust_wgpu_hello::scene_file::SceneFile;
+use egui_rust_wgpu_hello::{app, logging};
 use winit::event_loop::{ControlFlow, EventLoop};
 
+/// Fixed ticks simulated before a headless render, so the particles have
+/// spread out.
+#[cfg(not(target_arch = "wasm32"))]
+const HEADLESS_TICKS: u32 = 120;
+
 fn main() {
+    logging::init();
+
     #[cfg(not(target_arch = "wasm32"))]
     {
-        pollster::block_on(run());
+        let args: Vec<String> = std::env::args().collect();
+        if let Some(index) = args.iter().position(|arg| arg == "--headless") {
+            render_headless(&args[index + 1..]);
+            return;
+        }
+        let benchmark = args.iter().position(|arg| arg == "--bench").map(|index| {
+            let name = flag_value(&args, index).unwrap_or("orbit");
+            name.parse::<BenchScene>().unwrap_or_else(|err| {
+                eprintln!("{err}");
+                std::process::exit(2);
+            })
+        });
+        let remote = args.iter().position(|arg| arg == "--remote").map(|index| {
+            let addr = flag_value(&args, index).unwrap_or("127.0.0.1:9001");
+            RemoteControl::listen(addr).unwrap_or_else(|err| {
+                eprintln!("Failed to listen on {addr}: {err}");
+                std::process::exit(2);
+            })
+        });
+        pollster::block_on(run(benchmark, remote));
+    }
+    #[cfg(target_arch = "wasm32")]
+    {
+        console_error_panic_hook::set_once();
+        wasm_bindgen_futures::spawn_local(run(None));
     }
 }
 
-async fn run() {
-    let event_loop = EventLoop::new().unwrap();
+async fn run(
+    benchmark: Option<BenchScene>,
+    #[cfg(not(target_arch = "wasm32"))] remote: Option<RemoteControl>,
+) {
+    let event_loop = EventLoop::with_user_event().build().unwrap();
 
     event_loop.set_control_flow(ControlFlow::Poll);
 
-    let mut app = app::App::new();
+    let mut app = app::App::new(&event_loop);
+    if let Some(scene) = benchmark {
+        app = app.with_benchmark(scene);
+    }
+    #[cfg(not(target_arch = "wasm32"))]
+    if let Some(remote) = remote {
+        app = app.with_remote_control(remote);
+    }
 
+    #[cfg(not(target_arch = "wasm32"))]
     event_loop.run_app(&mut app).expect("Failed to run app");
+    #[cfg(target_arch = "wasm32")]
+    {
+        use winit::platform::web::EventLoopExtWebSys;
+        event_loop.spawn_app(app);
+    }
+}
+
+/// The optional value after the flag at `index`, unless the next argument is another flag.
+#[cfg(not(target_arch = "wasm32"))]
+fn flag_value(args: &[String], index: usize) -> Option<&str> {
+    args.get(index + 1)
+        .map(String::as_str)
+        .filter(|value| !value.starts_with("--"))
+}
+
+/// `--headless <output.png> [width] [height] [scene.ron]` renders a single
+/// frame of the scene, or of the default scene, without opening a window.
+#[cfg(not(target_arch = "wasm32"))]
+fn render_headless(args: &[String]) {
+    let path = args.first().map_or("render.png", String::as_str);
+    let width = parse_dimension(args.get(1), 1360, "width");
+    let height = parse_dimension(args.get(2), 768, "height");
+    let scene = match args.get(3) {
+        Some(scene_path) => SceneFile::load(scene_path).unwrap_or_else(|err| {
+            eprintln!("Failed to load scene {scene_path}: {err}");
+            std::process::exit(1);
+        }),
+        None => SceneFile::default(),
+    };
+
+    let renderer = pollster::block_on(headless::HeadlessRenderer::new()).unwrap_or_else(|err| {
+        eprintln!("Failed to set up rendering: {err}");
+        std::process::exit(1);
+    });
+    let image = renderer
+        .render_scene(&scene, HEADLESS_TICKS, width, height)
+        .unwrap_or_else(|err| {
+            eprintln!("Failed to read back the render: {err}");
+            std::process::exit(1);
+        });
+    if let Err(err) = image.save_png(path) {
+        eprintln!("Failed to save {path}: {err}");
+        std::process::exit(1);
+    }
+    println!("Saved {width}x{height} render to {path}");
+}
+
+/// Parses an image dimension for `--headless`, exiting with a usage error if it's invalid.
+#[cfg(not(target_arch = "wasm32"))]
+fn parse_dimension(arg: Option<&String>, default: u32, name: &str) -> u32 {
+    let Some(arg) = arg else {
+        return default;
+    };
+    let max = egui_wgpu::wgpu::Limits::default().max_texture_dimension_2d;
+    match arg.parse() {
+        Ok(value) if (1..=max).contains(&value) => value,
+        _ => {
+            eprintln!("Invalid {name} `{arg}`, expected a number from 1 to {max}");
+            eprintln!("Usage: --headless <output.png> [width] [height] [scene.ron]");
+            std::process::exit(2);
+        }
+    }
 }
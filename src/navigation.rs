@@ -0,0 +1,123 @@
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+
+/// A named position the camera can jump back to.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Waypoint {
+    pub name: String,
+    pub position: Vec3,
+}
+
+/// Coordinates readout, teleporting and waypoints. Waypoints are saved in scene files.
+pub struct Navigation {
+    pub waypoints: Vec<Waypoint>,
+    teleport_target: Vec3,
+    new_waypoint: String,
+}
+
+impl Default for Navigation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Navigation {
+    pub fn new() -> Self {
+        Self {
+            waypoints: Vec::new(),
+            teleport_target: Vec3::ZERO,
+            new_waypoint: String::new(),
+        }
+    }
+
+    pub fn find(&self, name: &str) -> Option<&Waypoint> {
+        self.waypoints.iter().find(|waypoint| waypoint.name == name)
+    }
+
+    /// Adds a waypoint, replacing any other one with the same name.
+    pub fn add(&mut self, name: &str, position: Vec3) {
+        self.waypoints.retain(|waypoint| waypoint.name != name);
+        self.waypoints.push(Waypoint {
+            name: name.to_owned(),
+            position,
+        });
+    }
+
+    /// Shows the navigation controls for a camera at `position`. Returns where
+    /// to teleport to if the user asked to.
+    pub fn ui(&mut self, ui: &mut egui::Ui, position: Vec3) -> Option<Vec3> {
+        let mut teleport = None;
+        ui.horizontal(|ui| {
+            let text = format!("{:.2}, {:.2}, {:.2}", position.x, position.y, position.z);
+            ui.monospace(&text);
+            if ui.small_button("Copy").clicked() {
+                ui.ctx().copy_text(text);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.add(egui::DragValue::new(&mut self.teleport_target.x).prefix("x "));
+            ui.add(egui::DragValue::new(&mut self.teleport_target.y).prefix("y "));
+            ui.add(egui::DragValue::new(&mut self.teleport_target.z).prefix("z "));
+            if ui.button("Teleport").clicked() {
+                teleport = Some(self.teleport_target);
+            }
+        });
+
+        ui.horizontal(|ui| {
+            ui.text_edit_singleline(&mut self.new_waypoint);
+            let name = self.new_waypoint.trim().to_owned();
+            if ui
+                .add_enabled(!name.is_empty(), egui::Button::new("Add waypoint"))
+                .clicked()
+            {
+                self.add(&name, position);
+                self.new_waypoint.clear();
+            }
+        });
+
+        let mut removed = None;
+        for (index, waypoint) in self.waypoints.iter().enumerate() {
+            ui.horizontal(|ui| {
+                ui.label(format!(
+                    "{} ({:.1} m)",
+                    waypoint.name,
+                    waypoint.position.distance(position)
+                ));
+                if ui.small_button("Go").clicked() {
+                    teleport = Some(waypoint.position);
+                }
+                if ui.small_button("Remove").clicked() {
+                    removed = Some(index);
+                }
+            });
+        }
+        if let Some(index) = removed {
+            self.waypoints.remove(index);
+        }
+        teleport
+    }
+
+    /// Lists the waypoints and their distance from `position` in a corner of the screen.
+    pub fn hud(&self, ctx: &egui::Context, position: Vec3) {
+        if self.waypoints.is_empty() {
+            return;
+        }
+        egui::Area::new(egui::Id::new("waypoint_hud"))
+            .anchor(egui::Align2::RIGHT_TOP, egui::vec2(-8.0, 8.0))
+            .interactable(false)
+            .show(ctx, |ui| {
+                for waypoint in &self.waypoints {
+                    ui.label(
+                        egui::RichText::new(format!(
+                            "{} {:.0} m",
+                            waypoint.name,
+                            waypoint.position.distance(position)
+                        ))
+                        .color(egui::Color32::WHITE)
+                        .background_color(egui::Color32::from_black_alpha(140)),
+                    );
+                }
+            });
+    }
+}
@@ -0,0 +1,309 @@
+use crate::camera::Camera;
+use crate::scene_file::ParticleSettings;
+use egui_wgpu::wgpu;
+use glam::Vec3;
+use std::collections::HashMap;
+
+const PARTICLE_COUNT: u32 = 4096;
+const WORKGROUP_SIZE: u32 = 64;
+
+/// Size of `Particle` in `particles.wgsl`: position and life, then velocity and padding.
+const PARTICLE_SIZE: u64 = 32;
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct SimParams {
+    emitter: [f32; 4],
+    dt: f32,
+    time: f32,
+    gravity: f32,
+    _padding: f32,
+}
+
+#[repr(C)]
+#[derive(Clone, Copy, bytemuck::Pod, bytemuck::Zeroable)]
+struct CameraParams {
+    view_proj: [[f32; 4]; 4],
+    camera_right: [f32; 4],
+    camera_up: [f32; 4],
+    size: f32,
+    _padding: [f32; 3],
+}
+
+/// Particles simulated in a compute shader and drawn as camera-facing sprites
+/// on top of the scene. The simulation only depends on the steps taken, so the
+/// same steps always give the same particles.
+pub struct ParticleSystem {
+    pub enabled: bool,
+    /// Keeps drawing the particles where they are without simulating them.
+    pub frozen: bool,
+    pub emitter: Vec3,
+    pub gravity: f32,
+    pub size: f32,
+    sim_params_buffer: wgpu::Buffer,
+    camera_params_buffer: wgpu::Buffer,
+    particle_buffer: wgpu::Buffer,
+    compute_bind_group: wgpu::BindGroup,
+    render_bind_group: wgpu::BindGroup,
+    compute_pipeline: wgpu::ComputePipeline,
+    render_pipeline: wgpu::RenderPipeline,
+    /// Simulated seconds, which also seed the respawns.
+    time: f32,
+}
+
+impl ParticleSystem {
+    pub fn new(device: &wgpu::Device, format: wgpu::TextureFormat) -> Self {
+        let shader = device.create_shader_module(wgpu::include_wgsl!("particles.wgsl"));
+
+        let sim_params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particle simulation params"),
+            size: std::mem::size_of::<SimParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        let camera_params_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particle camera params"),
+            size: std::mem::size_of::<CameraParams>() as u64,
+            usage: wgpu::BufferUsages::UNIFORM | wgpu::BufferUsages::COPY_DST,
+            mapped_at_creation: false,
+        });
+        // Zeroed particles are dead and respawn on the first update.
+        let particle_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("particles"),
+            size: PARTICLE_SIZE * PARTICLE_COUNT as u64,
+            usage: wgpu::BufferUsages::STORAGE | wgpu::BufferUsages::VERTEX,
+            mapped_at_creation: false,
+        });
+
+        let params_entry = |binding, visibility| wgpu::BindGroupLayoutEntry {
+            binding,
+            visibility,
+            ty: wgpu::BindingType::Buffer {
+                ty: wgpu::BufferBindingType::Uniform,
+                has_dynamic_offset: false,
+                min_binding_size: None,
+            },
+            count: None,
+        };
+        let compute_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particle compute layout"),
+            entries: &[
+                params_entry(0, wgpu::ShaderStages::COMPUTE),
+                wgpu::BindGroupLayoutEntry {
+                    binding: 1,
+                    visibility: wgpu::ShaderStages::COMPUTE,
+                    ty: wgpu::BindingType::Buffer {
+                        ty: wgpu::BufferBindingType::Storage { read_only: false },
+                        has_dynamic_offset: false,
+                        min_binding_size: None,
+                    },
+                    count: None,
+                },
+            ],
+        });
+        let render_layout = device.create_bind_group_layout(&wgpu::BindGroupLayoutDescriptor {
+            label: Some("particle render layout"),
+            entries: &[params_entry(2, wgpu::ShaderStages::VERTEX)],
+        });
+
+        let compute_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particle compute bind group"),
+            layout: &compute_layout,
+            entries: &[
+                wgpu::BindGroupEntry {
+                    binding: 0,
+                    resource: sim_params_buffer.as_entire_binding(),
+                },
+                wgpu::BindGroupEntry {
+                    binding: 1,
+                    resource: particle_buffer.as_entire_binding(),
+                },
+            ],
+        });
+        let render_bind_group = device.create_bind_group(&wgpu::BindGroupDescriptor {
+            label: Some("particle render bind group"),
+            layout: &render_layout,
+            entries: &[wgpu::BindGroupEntry {
+                binding: 2,
+                resource: camera_params_buffer.as_entire_binding(),
+            }],
+        });
+
+        let compute_pipeline = device.create_compute_pipeline(&wgpu::ComputePipelineDescriptor {
+            label: Some("particle simulation"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("particle compute pipeline layout"),
+                    bind_group_layouts: &[&compute_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            module: &shader,
+            entry_point: Some("simulate"),
+            compilation_options: Default::default(),
+            cache: None,
+        });
+        let render_pipeline = device.create_render_pipeline(&wgpu::RenderPipelineDescriptor {
+            label: Some("particle render"),
+            layout: Some(
+                &device.create_pipeline_layout(&wgpu::PipelineLayoutDescriptor {
+                    label: Some("particle render pipeline layout"),
+                    bind_group_layouts: &[&render_layout],
+                    push_constant_ranges: &[],
+                }),
+            ),
+            vertex: wgpu::VertexState {
+                module: &shader,
+                entry_point: Some("vs_main"),
+                compilation_options: Default::default(),
+                // Position and remaining life of each particle.
+                buffers: &[wgpu::VertexBufferLayout {
+                    array_stride: PARTICLE_SIZE,
+                    step_mode: wgpu::VertexStepMode::Instance,
+                    attributes: &wgpu::vertex_attr_array![0 => Float32x4],
+                }],
+            },
+            fragment: Some(wgpu::FragmentState {
+                module: &shader,
+                entry_point: Some("fs_main"),
+                compilation_options: wgpu::PipelineCompilationOptions {
+                    constants: &HashMap::from([(
+                        "SRGB_TARGET".to_owned(),
+                        if format.is_srgb() { 1.0 } else { 0.0 },
+                    )]),
+                    ..Default::default()
+                },
+                targets: &[Some(wgpu::ColorTargetState {
+                    format,
+                    blend: Some(wgpu::BlendState::ALPHA_BLENDING),
+                    write_mask: wgpu::ColorWrites::ALL,
+                })],
+            }),
+            primitive: wgpu::PrimitiveState::default(),
+            depth_stencil: None,
+            multisample: wgpu::MultisampleState::default(),
+            multiview: None,
+            cache: None,
+        });
+
+        Self {
+            enabled: true,
+            frozen: false,
+            emitter: Vec3::new(0.0, 2.0, -15.0),
+            gravity: 9.81,
+            size: 0.05,
+            sim_params_buffer,
+            camera_params_buffer,
+            particle_buffer,
+            compute_bind_group,
+            render_bind_group,
+            compute_pipeline,
+            render_pipeline,
+            time: 0.0,
+        }
+    }
+
+    /// The look of the particles, as saved in scene files.
+    pub fn settings(&self) -> ParticleSettings {
+        ParticleSettings {
+            enabled: self.enabled,
+            emitter: self.emitter,
+            gravity: self.gravity,
+            size: self.size,
+        }
+    }
+
+    /// Whether [`ParticleSystem::step`] moves the particles.
+    pub fn is_animating(&self) -> bool {
+        self.enabled && !self.frozen
+    }
+
+    pub fn apply_settings(&mut self, settings: &ParticleSettings) {
+        self.enabled = settings.enabled;
+        self.emitter = settings.emitter;
+        self.gravity = settings.gravity;
+        self.size = settings.size;
+    }
+
+    pub fn buffer_bytes(&self) -> u64 {
+        self.sim_params_buffer.size()
+            + self.camera_params_buffer.size()
+            + self.particle_buffer.size()
+    }
+
+    /// Simulates `dt` seconds and submits the work right away, so several steps
+    /// can be taken before a frame is drawn.
+    pub fn step(&mut self, device: &wgpu::Device, queue: &wgpu::Queue, dt: f32) {
+        if !self.is_animating() {
+            return;
+        }
+        self.time += dt;
+        let params = SimParams {
+            emitter: self.emitter.extend(1.0).into(),
+            dt,
+            time: self.time,
+            gravity: self.gravity,
+            _padding: 0.0,
+        };
+        queue.write_buffer(&self.sim_params_buffer, 0, bytemuck::bytes_of(&params));
+
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("particle simulation encoder"),
+        });
+        {
+            let mut pass = encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+                label: Some("particle simulation"),
+                timestamp_writes: None,
+            });
+            pass.set_pipeline(&self.compute_pipeline);
+            pass.set_bind_group(0, &self.compute_bind_group, &[]);
+            pass.dispatch_workgroups(PARTICLE_COUNT.div_ceil(WORKGROUP_SIZE), 1, 1);
+        }
+        queue.submit(Some(encoder.finish()));
+    }
+
+    /// Uploads the camera the next [`ParticleSystem::draw`] is seen from.
+    pub fn prepare(&self, queue: &wgpu::Queue, camera: &Camera, aspect: f32) {
+        if !self.enabled {
+            return;
+        }
+        let direction = camera.get_direction();
+        let right = direction.cross(Vec3::Y).normalize();
+        let up = right.cross(direction);
+        let params = CameraParams {
+            view_proj: (camera.get_projection_matrix(aspect) * camera.get_view_matrix())
+                .to_cols_array_2d(),
+            camera_right: right.extend(0.0).into(),
+            camera_up: up.extend(0.0).into(),
+            size: self.size,
+            _padding: [0.0; 3],
+        };
+        queue.write_buffer(&self.camera_params_buffer, 0, bytemuck::bytes_of(&params));
+    }
+
+    /// Draws the particles over whatever is already in `view`.
+    pub fn draw(&self, encoder: &mut wgpu::CommandEncoder, view: &wgpu::TextureView) {
+        if !self.enabled {
+            return;
+        }
+
+        let mut pass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("particle pass"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Load,
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+        pass.set_pipeline(&self.render_pipeline);
+        pass.set_bind_group(0, &self.render_bind_group, &[]);
+        pass.set_vertex_buffer(0, self.particle_buffer.slice(..));
+        pass.draw(0..6, 0..PARTICLE_COUNT);
+    }
+}
@@ -0,0 +1,107 @@
+struct Particle {
+    position: vec3<f32>,
+    life: f32,
+    velocity: vec3<f32>,
+    _padding: f32,
+}
+
+struct SimParams {
+    emitter: vec4<f32>,
+    dt: f32,
+    time: f32,
+    gravity: f32,
+}
+
+struct CameraParams {
+    view_proj: mat4x4<f32>,
+    camera_right: vec4<f32>,
+    camera_up: vec4<f32>,
+    size: f32,
+}
+
+// Bindings 0 and 1 are used by the simulation, binding 2 by the sprites.
+@group(0) @binding(0) var<uniform> params: SimParams;
+@group(0) @binding(1) var<storage, read_write> particles: array<Particle>;
+@group(0) @binding(2) var<uniform> camera: CameraParams;
+
+fn hash(n: u32) -> f32 {
+    var x = n;
+    x = (x ^ 61u) ^ (x >> 16u);
+    x = x * 9u;
+    x = x ^ (x >> 4u);
+    x = x * 0x27d4eb2du;
+    x = x ^ (x >> 15u);
+    return f32(x) / 4294967295.0;
+}
+
+@compute @workgroup_size(64)
+fn simulate(@builtin(global_invocation_id) id: vec3<u32>) {
+    let i = id.x;
+    if i >= arrayLength(&particles) {
+        return;
+    }
+
+    var p = particles[i];
+    p.life -= params.dt;
+    if p.life <= 0.0 {
+        // Respawn at the emitter with a random upward velocity.
+        let seed = i * 747796405u + u32(params.time * 1000.0) * 2891336453u;
+        let angle = hash(seed) * 6.2831853;
+        let spread = hash(seed + 1u) * 2.0;
+        p.position = params.emitter.xyz;
+        p.velocity = vec3<f32>(cos(angle) * spread, 6.0 + hash(seed + 2u) * 4.0, sin(angle) * spread);
+        p.life = 1.0 + hash(seed + 3u) * 2.0;
+    } else {
+        p.velocity.y -= params.gravity * params.dt;
+        p.position += p.velocity * params.dt;
+    }
+    particles[i] = p;
+}
+
+struct VertexOutput {
+    @builtin(position) clip_position: vec4<f32>,
+    @location(0) corner: vec2<f32>,
+    @location(1) alpha: f32,
+}
+
+@vertex
+fn vs_main(@builtin(vertex_index) vertex: u32, @location(0) particle: vec4<f32>) -> VertexOutput {
+    var corners = array<vec2<f32>, 6>(
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, -1.0),
+        vec2<f32>(1.0, 1.0),
+        vec2<f32>(-1.0, 1.0),
+    );
+    let corner = corners[vertex];
+    let offset = camera.camera_right.xyz * corner.x + camera.camera_up.xyz * corner.y;
+
+    var out: VertexOutput;
+    out.clip_position = camera.view_proj * vec4<f32>(particle.xyz + offset * camera.size, 1.0);
+    out.corner = corner;
+    out.alpha = clamp(particle.w, 0.0, 1.0);
+    return out;
+}
+
+// Whether the render target encodes sRGB itself. Otherwise the shader does.
+override SRGB_TARGET: bool = true;
+
+fn linear_to_srgb(color: vec3<f32>) -> vec3<f32> {
+    let low = color * 12.92;
+    let high = 1.055 * pow(color, vec3<f32>(1.0 / 2.4)) - 0.055;
+    return select(high, low, color <= vec3<f32>(0.0031308));
+}
+
+@fragment
+fn fs_main(in: VertexOutput) -> @location(0) vec4<f32> {
+    let distance = length(in.corner);
+    if distance > 1.0 {
+        discard;
+    }
+    var color = vec3<f32>(1.0, 0.85, 0.6);
+    if !SRGB_TARGET {
+        color = linear_to_srgb(color);
+    }
+    return vec4<f32>(color, in.alpha * (1.0 - distance));
+}
@@ -0,0 +1,520 @@
+use egui_wgpu::wgpu;
+use std::collections::VecDeque;
+use std::sync::mpsc;
+use std::time::Duration;
+use web_time::Instant;
+
+/// How much history the statistics and the graph cover.
+const WINDOW: Duration = Duration::from_secs(5);
+
+struct FrameSample {
+    time: Instant,
+    frame_ms: f32,
+    cpu_ms: f32,
+    gpu_ms: Option<f32>,
+    latency_ms: f32,
+}
+
+/// Rolling frame timing statistics over the last few seconds.
+pub struct FrameStats {
+    samples: VecDeque<FrameSample>,
+}
+
+impl Default for FrameStats {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameStats {
+    pub fn new() -> Self {
+        Self {
+            samples: VecDeque::new(),
+        }
+    }
+
+    /// `latency` is the time from sampling input at the start of the frame until it was presented.
+    pub fn push(
+        &mut self,
+        frame_time: Duration,
+        cpu_time: Duration,
+        gpu_time: Option<Duration>,
+        latency: Duration,
+    ) {
+        let now = Instant::now();
+        self.samples.push_back(FrameSample {
+            time: now,
+            frame_ms: frame_time.as_secs_f32() * 1000.0,
+            cpu_ms: cpu_time.as_secs_f32() * 1000.0,
+            gpu_ms: gpu_time.map(|t| t.as_secs_f32() * 1000.0),
+            latency_ms: latency.as_secs_f32() * 1000.0,
+        });
+        while self
+            .samples
+            .front()
+            .is_some_and(|s| now.duration_since(s.time) > WINDOW)
+        {
+            self.samples.pop_front();
+        }
+    }
+
+    pub fn fps(&self) -> f32 {
+        let total_ms: f32 = self.samples.iter().map(|s| s.frame_ms).sum();
+        if total_ms > 0.0 {
+            self.samples.len() as f32 * 1000.0 / total_ms
+        } else {
+            0.0
+        }
+    }
+
+    pub fn average_latency_ms(&self) -> f32 {
+        if self.samples.is_empty() {
+            return 0.0;
+        }
+        self.samples.iter().map(|s| s.latency_ms).sum::<f32>() / self.samples.len() as f32
+    }
+
+    /// Drops all samples, e.g. after a change that makes older ones meaningless.
+    pub fn clear(&mut self) {
+        self.samples.clear();
+    }
+
+    /// Average FPS of the slowest 1% of frames in the window.
+    pub fn one_percent_low(&self) -> f32 {
+        let mut frame_times: Vec<f32> = self.samples.iter().map(|s| s.frame_ms).collect();
+        if frame_times.is_empty() {
+            return 0.0;
+        }
+        frame_times.sort_by(|a, b| b.total_cmp(a));
+        let worst = &frame_times[..frame_times.len().div_ceil(100)];
+        let average_ms = worst.iter().sum::<f32>() / worst.len() as f32;
+        if average_ms > 0.0 {
+            1000.0 / average_ms
+        } else {
+            0.0
+        }
+    }
+
+    fn latest(&self) -> Option<&FrameSample> {
+        self.samples.back()
+    }
+
+    pub fn ui(&self, ui: &mut egui::Ui) {
+        ui.label(format!(
+            "FPS: {:.0} (1% low: {:.0})",
+            self.fps(),
+            self.one_percent_low()
+        ));
+        if let Some(latest) = self.latest() {
+            let gpu = latest
+                .gpu_ms
+                .map_or("n/a".to_owned(), |ms| format!("{ms:.2} ms"));
+            ui.label(format!(
+                "Frame {:.2} ms | CPU {:.2} ms | GPU {gpu}",
+                latest.frame_ms, latest.cpu_ms
+            ));
+            ui.label(format!(
+                "Input to present: {:.2} ms",
+                self.average_latency_ms()
+            ));
+        }
+        self.graph(ui);
+    }
+
+    fn graph(&self, ui: &mut egui::Ui) {
+        let size = egui::vec2(ui.available_width(), 80.0);
+        let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+        let painter = ui.painter_at(rect);
+        painter.rect_filled(rect, 2.0, ui.visuals().extreme_bg_color);
+
+        let Some(latest) = self.latest() else {
+            return;
+        };
+        // Scale to at least 30 FPS so a steady frame rate doesn't fill the graph.
+        let max_ms = self
+            .samples
+            .iter()
+            .map(|s| s.frame_ms)
+            .fold(1000.0 / 30.0, f32::max);
+        let to_screen = |time: Instant, ms: f32| {
+            let age = latest.time.duration_since(time).as_secs_f32() / WINDOW.as_secs_f32();
+            egui::pos2(
+                rect.right() - age * rect.width(),
+                rect.bottom() - ms / max_ms * rect.height(),
+            )
+        };
+
+        let target_y = to_screen(latest.time, 1000.0 / 60.0).y;
+        painter.hline(
+            rect.x_range(),
+            target_y,
+            egui::Stroke::new(1.0, ui.visuals().weak_text_color()),
+        );
+
+        let line = |value: fn(&FrameSample) -> Option<f32>, color: egui::Color32| {
+            let points: Vec<_> = self
+                .samples
+                .iter()
+                .filter_map(|s| value(s).map(|ms| to_screen(s.time, ms)))
+                .collect();
+            painter.add(egui::Shape::line(points, egui::Stroke::new(1.0, color)));
+        };
+        line(|s| Some(s.frame_ms), egui::Color32::LIGHT_GREEN);
+        line(|s| Some(s.cpu_ms), egui::Color32::LIGHT_BLUE);
+        line(|s| s.gpu_ms, egui::Color32::ORANGE);
+    }
+}
+
+/// How long before a frame is due the limiter stops sleeping and spins, to make
+/// up for the coarse timer resolution of the OS.
+const SPIN_MARGIN: Duration = Duration::from_millis(2);
+
+/// Caps the frame rate independently of vsync. The event loop sleeps until just
+/// before the next frame is due and [`FrameLimiter::wait`] spins for the rest,
+/// which paces frames more evenly than sleeping alone. Browsers only get the sleep.
+pub struct FrameLimiter {
+    deadline: Option<Instant>,
+}
+
+impl Default for FrameLimiter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl FrameLimiter {
+    pub fn new() -> Self {
+        Self { deadline: None }
+    }
+
+    /// Schedules the next frame for a cap of `fps` and returns when to wake up
+    /// for it, or `None` if `fps` is 0 and frames are uncapped.
+    pub fn schedule(&mut self, fps: u32) -> Option<Instant> {
+        if fps == 0 {
+            self.deadline = None;
+            return None;
+        }
+        let interval = Duration::from_secs_f64(1.0 / fps as f64);
+        let now = Instant::now();
+        // Keep a steady cadence, but start over instead of rushing frames to catch up.
+        let deadline = match self.deadline {
+            Some(deadline) if deadline + interval > now => deadline + interval,
+            _ => now + interval,
+        };
+        self.deadline = Some(deadline);
+        Some(deadline.checked_sub(SPIN_MARGIN).unwrap_or(deadline))
+    }
+
+    /// Spins until the scheduled frame is due and returns `None` when it is. If
+    /// it's further away than the spin margin, e.g. because input woke the event
+    /// loop early, returns when to wake up for it instead, without spinning.
+    pub fn wait(&self) -> Option<Instant> {
+        let deadline = self.deadline?;
+        if deadline > Instant::now() + SPIN_MARGIN {
+            return Some(deadline - SPIN_MARGIN);
+        }
+        // Spinning would block the page in the browser.
+        #[cfg(not(target_arch = "wasm32"))]
+        while Instant::now() < deadline {
+            std::hint::spin_loop();
+        }
+        None
+    }
+}
+
+/// Measures GPU time of a frame with timestamp queries written by empty compute
+/// passes at the start and end of the command encoder.
+pub struct GpuTimer {
+    query_set: wgpu::QuerySet,
+    resolve_buffer: wgpu::Buffer,
+    readback_buffer: wgpu::Buffer,
+    period_ns: f32,
+    /// Set while timestamps are written this frame.
+    active: bool,
+    /// Present while the readback buffer is being mapped.
+    map_result: Option<mpsc::Receiver<Result<(), wgpu::BufferAsyncError>>>,
+    last: Option<Duration>,
+}
+
+impl GpuTimer {
+    /// Returns `None` if the device was created without `TIMESTAMP_QUERY`.
+    pub fn new(device: &wgpu::Device, queue: &wgpu::Queue) -> Option<Self> {
+        if !device.features().contains(wgpu::Features::TIMESTAMP_QUERY) {
+            return None;
+        }
+
+        let query_set = device.create_query_set(&wgpu::QuerySetDescriptor {
+            label: Some("gpu timer queries"),
+            ty: wgpu::QueryType::Timestamp,
+            count: 2,
+        });
+        let size = 2 * std::mem::size_of::<u64>() as u64;
+        let resolve_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("gpu timer resolve"),
+            size,
+            usage: wgpu::BufferUsages::QUERY_RESOLVE | wgpu::BufferUsages::COPY_SRC,
+            mapped_at_creation: false,
+        });
+        let readback_buffer = device.create_buffer(&wgpu::BufferDescriptor {
+            label: Some("gpu timer readback"),
+            size,
+            usage: wgpu::BufferUsages::COPY_DST | wgpu::BufferUsages::MAP_READ,
+            mapped_at_creation: false,
+        });
+
+        Some(Self {
+            query_set,
+            resolve_buffer,
+            readback_buffer,
+            period_ns: queue.get_timestamp_period(),
+            active: false,
+            map_result: None,
+            last: None,
+        })
+    }
+
+    fn write_timestamp(&self, encoder: &mut wgpu::CommandEncoder, index: u32) {
+        encoder.begin_compute_pass(&wgpu::ComputePassDescriptor {
+            label: Some("gpu timer"),
+            timestamp_writes: Some(wgpu::ComputePassTimestampWrites {
+                query_set: &self.query_set,
+                beginning_of_pass_write_index: Some(index),
+                end_of_pass_write_index: None,
+            }),
+        });
+    }
+
+    /// Starts timing unless the previous measurement is still being read back.
+    pub fn begin(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        self.active = self.map_result.is_none();
+        if self.active {
+            self.write_timestamp(encoder, 0);
+        }
+    }
+
+    pub fn end(&mut self, encoder: &mut wgpu::CommandEncoder) {
+        if !self.active {
+            return;
+        }
+        self.write_timestamp(encoder, 1);
+        encoder.resolve_query_set(&self.query_set, 0..2, &self.resolve_buffer, 0);
+        encoder.copy_buffer_to_buffer(
+            &self.resolve_buffer,
+            0,
+            &self.readback_buffer,
+            0,
+            self.resolve_buffer.size(),
+        );
+    }
+
+    /// Must be called after the encoder passed to `end` has been submitted.
+    pub fn submitted(&mut self) {
+        if !self.active {
+            return;
+        }
+        self.active = false;
+
+        let (sender, receiver) = mpsc::channel();
+        self.readback_buffer
+            .slice(..)
+            .map_async(wgpu::MapMode::Read, move |result| {
+                let _ = sender.send(result);
+            });
+        self.map_result = Some(receiver);
+    }
+
+    pub fn buffer_bytes(&self) -> u64 {
+        self.resolve_buffer.size() + self.readback_buffer.size()
+    }
+
+    /// Picks up a finished measurement, if any, and returns the latest GPU frame time.
+    pub fn poll(&mut self, device: &wgpu::Device) -> Option<Duration> {
+        let Some(receiver) = &self.map_result else {
+            return self.last;
+        };
+        device.poll(wgpu::Maintain::Poll);
+
+        let Ok(result) = receiver.try_recv() else {
+            return self.last;
+        };
+        self.map_result = None;
+        if result.is_ok() {
+            {
+                let data = self.readback_buffer.slice(..).get_mapped_range();
+                let timestamps: &[u64] = bytemuck::cast_slice(&data);
+                let ticks = timestamps[1].saturating_sub(timestamps[0]);
+                self.last = Some(Duration::from_nanos(
+                    (ticks as f64 * self.period_ns as f64) as u64,
+                ));
+            }
+            self.readback_buffer.unmap();
+        }
+        self.last
+    }
+}
+
+/// How often the allocator report is regenerated, since it copies every allocation.
+const MEMORY_REFRESH: Duration = Duration::from_secs(1);
+/// Fraction of a device limit at which a buffer is flagged.
+const LIMIT_WARNING: f64 = 0.8;
+
+/// GPU memory use, grouped by resource label. Totals come from the backend's
+/// allocator where it can report them (Vulkan, DX12); the tracked buffers are
+/// sizes the app knows about itself and are shown on every backend.
+pub struct GpuMemory {
+    allocated: Option<(u64, u64)>,
+    by_label: Vec<(String, u64)>,
+    updated_at: Option<Instant>,
+}
+
+impl Default for GpuMemory {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GpuMemory {
+    pub fn new() -> Self {
+        Self {
+            allocated: None,
+            by_label: Vec::new(),
+            updated_at: None,
+        }
+    }
+
+    pub fn update(&mut self, device: &wgpu::Device) {
+        if self
+            .updated_at
+            .is_some_and(|updated_at| updated_at.elapsed() < MEMORY_REFRESH)
+        {
+            return;
+        }
+        self.updated_at = Some(Instant::now());
+
+        let Some(report) = device.generate_allocator_report() else {
+            self.allocated = None;
+            self.by_label.clear();
+            return;
+        };
+        self.allocated = Some((report.total_allocated_bytes, report.total_reserved_bytes));
+        let mut by_label: Vec<(String, u64)> = Vec::new();
+        for allocation in report.allocations {
+            let label = if allocation.name.is_empty() {
+                "unlabeled".to_owned()
+            } else {
+                allocation.name
+            };
+            match by_label.iter_mut().find(|(name, _)| *name == label) {
+                Some((_, size)) => *size += allocation.size,
+                None => by_label.push((label, allocation.size)),
+            }
+        }
+        by_label.sort_by_key(|(_, size)| std::cmp::Reverse(*size));
+        self.by_label = by_label;
+    }
+
+    /// `tracked` lists buffers by name and size in bytes.
+    pub fn ui(&self, ui: &mut egui::Ui, limits: &wgpu::Limits, tracked: &[(&str, u64)]) {
+        match self.allocated {
+            Some((allocated, reserved)) => {
+                ui.label(format!(
+                    "Allocated {} of {} reserved",
+                    format_bytes(allocated),
+                    format_bytes(reserved)
+                ));
+            }
+            None => {
+                ui.weak("This backend doesn't report allocations");
+            }
+        }
+
+        let near_limit = |size: u64| size as f64 > limits.max_buffer_size as f64 * LIMIT_WARNING;
+        egui::Grid::new("gpu_memory").striped(true).show(ui, |ui| {
+            let rows = tracked.iter().map(|(name, size)| (*name, *size)).chain(
+                self.by_label
+                    .iter()
+                    .map(|(name, size)| (name.as_str(), *size)),
+            );
+            for (name, size) in rows {
+                ui.label(name);
+                if near_limit(size) {
+                    ui.colored_label(
+                        egui::Color32::YELLOW,
+                        format!("{} (near buffer size limit)", format_bytes(size)),
+                    );
+                } else {
+                    ui.label(format_bytes(size));
+                }
+                ui.end_row();
+            }
+        });
+    }
+}
+
+fn format_bytes(bytes: u64) -> String {
+    const UNITS: [&str; 4] = ["B", "KiB", "MiB", "GiB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    format!("{value:.1} {}", UNITS[unit])
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn stats_with(frame_ms: &[u64]) -> FrameStats {
+        let mut stats = FrameStats::new();
+        for &ms in frame_ms {
+            let frame_time = Duration::from_millis(ms);
+            stats.push(frame_time, frame_time, None, frame_time);
+        }
+        stats
+    }
+
+    #[test]
+    fn one_percent_low_without_samples_is_zero() {
+        assert_eq!(FrameStats::new().one_percent_low(), 0.0);
+    }
+
+    #[test]
+    fn one_percent_low_of_a_few_samples_is_the_slowest_frame() {
+        assert_eq!(stats_with(&[20]).one_percent_low(), 50.0);
+        assert_eq!(stats_with(&[10, 40, 10, 20, 10]).one_percent_low(), 25.0);
+    }
+
+    #[test]
+    fn one_percent_low_rounds_the_slowest_frames_up() {
+        // 101 samples: the slowest 1% is two frames, not one.
+        let mut frame_ms = vec![10; 99];
+        frame_ms.extend([20, 30]);
+        assert_eq!(stats_with(&frame_ms).one_percent_low(), 40.0);
+    }
+
+    #[test]
+    fn uncapped_frames_never_wait() {
+        let mut limiter = FrameLimiter::new();
+        assert_eq!(limiter.wait(), None);
+        assert_eq!(limiter.schedule(0), None);
+        assert_eq!(limiter.wait(), None);
+    }
+
+    #[test]
+    fn early_frames_wait_instead_of_spinning() {
+        let mut limiter = FrameLimiter::new();
+        let wake_at = limiter.schedule(10).unwrap();
+        // A frame requested right away is 100 ms early.
+        assert_eq!(limiter.wait(), Some(wake_at));
+        assert!(wake_at > Instant::now() + Duration::from_millis(50));
+    }
+
+    #[test]
+    fn zero_length_frames_do_not_divide_by_zero() {
+        let stats = stats_with(&[0, 0]);
+        assert_eq!(stats.one_percent_low(), 0.0);
+        assert_eq!(stats.fps(), 0.0);
+    }
+}
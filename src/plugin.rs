@@ -0,0 +1,25 @@
+use crate::camera::Camera;
+use egui_wgpu::wgpu;
+
+/// Hooks for extending the app without editing `app.rs`. Register plugins with
+/// [`App::add_plugin`](crate::App::add_plugin); every hook has an empty default.
+pub trait EnginePlugin {
+    /// Called once the device exists and again whenever the adapter is switched,
+    /// so GPU resources should be (re)created here.
+    fn setup(
+        &mut self,
+        _device: &wgpu::Device,
+        _queue: &wgpu::Queue,
+        _format: wgpu::TextureFormat,
+    ) {
+    }
+
+    /// Called every fixed tick after the camera has been moved by input.
+    fn update(&mut self, _dt: f32, _camera: &mut Camera) {}
+
+    /// Called every frame while egui is building the UI.
+    fn ui(&mut self, _ctx: &egui::Context) {}
+
+    /// Called every frame after the scene and before egui is drawn into `view`.
+    fn render(&mut self, _encoder: &mut wgpu::CommandEncoder, _view: &wgpu::TextureView) {}
+}
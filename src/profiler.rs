@@ -0,0 +1,179 @@
+use puffin::{FrameData, GlobalFrameView, Reader, ScopeCollection, Stream};
+use std::sync::Arc;
+
+const ROW_HEIGHT: f32 = 18.0;
+
+/// Shows the puffin scopes of a frame as a flamegraph. Scopes are only recorded
+/// while the window is open.
+pub struct Profiler {
+    view: Option<GlobalFrameView>,
+    /// Frame kept on screen while paused.
+    paused: Option<Arc<FrameData>>,
+}
+
+impl Default for Profiler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Profiler {
+    pub fn new() -> Self {
+        Self {
+            view: None,
+            paused: None,
+        }
+    }
+
+    /// Marks the start of a new frame. Call once per frame before any scopes.
+    pub fn new_frame() {
+        puffin::GlobalProfiler::lock().new_frame();
+    }
+
+    pub fn is_open(&self) -> bool {
+        self.view.is_some()
+    }
+
+    pub fn set_open(&mut self, open: bool) {
+        if open == self.is_open() {
+            return;
+        }
+        puffin::set_scopes_on(open);
+        self.view = open.then(GlobalFrameView::default);
+        self.paused = None;
+    }
+
+    pub fn ui(&mut self, ctx: &egui::Context) {
+        if !self.is_open() {
+            return;
+        }
+        let mut open = true;
+        egui::Window::new("Profiler")
+            .open(&mut open)
+            .default_size([700.0, 200.0])
+            .show(ctx, |ui| self.contents(ui));
+        if !open {
+            self.set_open(false);
+        }
+    }
+
+    /// Fills the whole of `ctx` with the profiler, for a window of its own.
+    pub fn panel_ui(&mut self, ctx: &egui::Context) {
+        egui::CentralPanel::default().show(ctx, |ui| {
+            egui::ScrollArea::vertical().show(ui, |ui| self.contents(ui));
+        });
+    }
+
+    fn contents(&mut self, ui: &mut egui::Ui) {
+        let Some(view) = &self.view else {
+            return;
+        };
+        let mut paused = self.paused.is_some();
+        let view = view.lock();
+        let frame = self.paused.clone().or_else(|| view.latest_frame());
+        ui.horizontal(|ui| {
+            ui.checkbox(&mut paused, "Pause");
+            if let Some(frame) = &frame {
+                ui.label(format!(
+                    "Frame {} took {:.2} ms",
+                    frame.frame_index(),
+                    frame.duration_ns() as f64 * 1e-6
+                ));
+            }
+        });
+        if paused && self.paused.is_none() {
+            self.paused = frame.clone();
+        } else if !paused {
+            self.paused = None;
+        }
+
+        let Some(frame) = frame else {
+            ui.label("Waiting for a frame...");
+            return;
+        };
+        let Some(unpacked) = frame.unpacked().ok() else {
+            ui.label("Failed to unpack the frame");
+            return;
+        };
+        let range_ns = unpacked.range_ns();
+        for (thread, stream_info) in &unpacked.thread_streams {
+            ui.label(&thread.name);
+            let height = stream_info.depth.max(1) as f32 * ROW_HEIGHT;
+            let size = egui::vec2(ui.available_width(), height);
+            let (rect, _) = ui.allocate_exact_size(size, egui::Sense::hover());
+            let graph = Flamegraph {
+                ui,
+                scopes: view.scope_collection(),
+                rect,
+                range_ns,
+            };
+            graph.draw(
+                &stream_info.stream,
+                Reader::from_start(&stream_info.stream),
+                0,
+            );
+        }
+    }
+}
+
+struct Flamegraph<'a> {
+    ui: &'a egui::Ui,
+    scopes: &'a ScopeCollection,
+    rect: egui::Rect,
+    range_ns: (i64, i64),
+}
+
+impl Flamegraph<'_> {
+    fn x(&self, ns: i64) -> f32 {
+        let (start, end) = self.range_ns;
+        let t = (ns - start) as f32 / (end - start).max(1) as f32;
+        self.rect.left() + t * self.rect.width()
+    }
+
+    fn draw(&self, stream: &Stream, reader: Reader<'_>, depth: usize) {
+        for scope in reader.map_while(Result::ok) {
+            let name = self
+                .scopes
+                .fetch_by_id(&scope.id)
+                .map_or("?", |details| details.name().as_ref());
+            let top = self.rect.top() + depth as f32 * ROW_HEIGHT;
+            let rect = egui::Rect::from_min_max(
+                egui::pos2(self.x(scope.record.start_ns), top),
+                egui::pos2(self.x(scope.record.stop_ns()), top + ROW_HEIGHT - 1.0),
+            );
+            let duration_ms = scope.record.duration_ns as f64 * 1e-6;
+
+            let painter = self.ui.painter_at(self.rect);
+            painter.rect_filled(rect, 2.0, scope_color(name));
+            if rect.width() > 40.0 {
+                painter.with_clip_rect(rect).text(
+                    rect.left_center() + egui::vec2(4.0, 0.0),
+                    egui::Align2::LEFT_CENTER,
+                    format!("{name} {duration_ms:.2} ms"),
+                    egui::FontId::proportional(12.0),
+                    egui::Color32::BLACK,
+                );
+            }
+            if self.ui.rect_contains_pointer(rect) {
+                egui::show_tooltip_at_pointer(
+                    self.ui.ctx(),
+                    self.ui.layer_id(),
+                    egui::Id::new("profiler_tooltip"),
+                    |ui| ui.label(format!("{name}: {duration_ms:.3} ms")),
+                );
+            }
+
+            if let Ok(children) = Reader::with_offset(stream, scope.child_begin_position) {
+                self.draw(stream, children, depth + 1);
+            }
+        }
+    }
+}
+
+/// Gives each scope name a stable pastel color.
+fn scope_color(name: &str) -> egui::Color32 {
+    let hash = name.bytes().fold(2166136261u32, |hash, b| {
+        (hash ^ b as u32).wrapping_mul(16777619)
+    });
+    egui::ecolor::Hsva::new((hash % 360) as f32 / 360.0, 0.45, 0.9, 1.0).into()
+}
@@ -0,0 +1,154 @@
+use crate::camera::Camera;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader, BufWriter, Write};
+use std::path::Path;
+use web_time::Instant;
+use winit::keyboard::KeyCode;
+
+/// Everything that drives the simulation for a single tick.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct InputFrame {
+    /// Seconds since the recording started.
+    pub time: f64,
+    pub keys: Vec<KeyCode>,
+    pub mouse_delta: (f32, f32),
+    pub touch_movement: (f32, f32),
+    pub touch_look: (f32, f32),
+}
+
+/// Captures input frames together with the camera they started from, so a
+/// replay reproduces the exact same camera path.
+pub struct InputRecorder {
+    started_at: Instant,
+    camera: Camera,
+    frames: Vec<InputFrame>,
+}
+
+impl InputRecorder {
+    pub fn new(camera: &Camera) -> Self {
+        Self {
+            started_at: Instant::now(),
+            camera: camera.clone(),
+            frames: Vec::new(),
+        }
+    }
+
+    pub fn record(&mut self, frame: &InputFrame) {
+        self.frames.push(InputFrame {
+            time: self.started_at.elapsed().as_secs_f64(),
+            ..frame.clone()
+        });
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.frames.len()
+    }
+
+    /// Writes the recording as JSON lines: the starting camera followed by one tick per line.
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer(&mut writer, &self.camera)?;
+        writeln!(writer)?;
+        for frame in &self.frames {
+            serde_json::to_writer(&mut writer, frame)?;
+            writeln!(writer)?;
+        }
+        writer.flush()
+    }
+}
+
+pub struct InputPlayback {
+    frames: VecDeque<InputFrame>,
+}
+
+impl InputPlayback {
+    /// Loads a recording, returning the camera it must be replayed from.
+    pub fn load(path: impl AsRef<Path>) -> io::Result<(Camera, Self)> {
+        let mut lines = BufReader::new(File::open(path)?).lines();
+        let header = lines
+            .next()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, "empty recording"))??;
+        let camera = serde_json::from_str(&header)?;
+        let frames = lines
+            .map(|line| Ok(serde_json::from_str(&line?)?))
+            .collect::<io::Result<_>>()?;
+
+        Ok((camera, Self { frames }))
+    }
+
+    pub fn next_frame(&mut self) -> Option<InputFrame> {
+        self.frames.pop_front()
+    }
+
+    pub fn remaining(&self) -> usize {
+        self.frames.len()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::CameraSettings;
+    use glam::Vec3;
+
+    #[test]
+    fn recording_round_trips_through_a_file() {
+        let mut camera = Camera::new(&CameraSettings::default());
+        camera.set_position(Vec3::new(1.0, 2.0, 3.0));
+        camera.handle_mouse(&(12.5, -4.0));
+
+        let mut recorder = InputRecorder::new(&camera);
+        let frames = [
+            InputFrame {
+                keys: vec![KeyCode::KeyW, KeyCode::ShiftLeft],
+                mouse_delta: (3.0, -1.5),
+                ..Default::default()
+            },
+            InputFrame::default(),
+            InputFrame {
+                touch_movement: (0.25, 1.0),
+                touch_look: (-2.0, 0.5),
+                ..Default::default()
+            },
+        ];
+        for frame in &frames {
+            recorder.record(frame);
+        }
+
+        let path = std::env::temp_dir().join(format!("recording-{}.jsonl", std::process::id()));
+        recorder.save(&path).unwrap();
+        let loaded = InputPlayback::load(&path);
+        std::fs::remove_file(&path).unwrap();
+        let (loaded_camera, mut playback) = loaded.unwrap();
+
+        assert_eq!(loaded_camera, camera);
+        assert_eq!(playback.remaining(), frames.len());
+        let mut previous_time = 0.0;
+        for expected in &frames {
+            let frame = playback.next_frame().unwrap();
+            assert!(frame.time >= previous_time);
+            previous_time = frame.time;
+            assert_eq!(
+                frame,
+                InputFrame {
+                    time: frame.time,
+                    ..expected.clone()
+                }
+            );
+        }
+        assert!(playback.next_frame().is_none());
+    }
+
+    #[test]
+    fn empty_recording_is_invalid() {
+        let path =
+            std::env::temp_dir().join(format!("empty-recording-{}.jsonl", std::process::id()));
+        std::fs::write(&path, "").unwrap();
+        let loaded = InputPlayback::load(&path);
+        std::fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.err().unwrap().kind(), io::ErrorKind::InvalidData);
+    }
+}
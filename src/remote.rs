@@ -0,0 +1,181 @@
+use std::io;
+use std::net::{TcpListener, TcpStream, ToSocketAddrs};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Arc;
+use std::thread;
+use tungstenite::handshake::server::{ErrorResponse, Response};
+use tungstenite::http::StatusCode;
+use tungstenite::Message;
+
+/// A command line received from a remote client, answered with [`Request::respond`].
+pub struct Request {
+    pub line: String,
+    reply: Sender<String>,
+}
+
+impl Request {
+    pub fn respond(self, result: Result<String, String>) {
+        let reply = match result {
+            Ok(output) => serde_json::json!({ "ok": true, "output": output }),
+            Err(error) => serde_json::json!({ "ok": false, "error": error }),
+        };
+        // The client may have disconnected in the meantime.
+        let _ = self.reply.send(reply.to_string());
+    }
+}
+
+/// A WebSocket endpoint that lets scripts and test rigs run console commands.
+/// Every text message is one command line, answered with a JSON object holding
+/// either `output` or `error`.
+///
+/// Clients must pass the token generated at launch, as `?token=` in the URL or
+/// as an `Authorization: Bearer` header. Connections from web pages, which
+/// always send an `Origin` header, are refused.
+pub struct RemoteControl {
+    requests: Receiver<Request>,
+}
+
+impl RemoteControl {
+    /// Starts accepting connections on a background thread.
+    pub fn listen(addr: impl ToSocketAddrs) -> io::Result<Self> {
+        let listener = TcpListener::bind(addr)?;
+        let mut bytes = [0; 16];
+        getrandom::fill(&mut bytes).map_err(io::Error::other)?;
+        let token: Arc<str> = bytes
+            .iter()
+            .map(|b| format!("{b:02x}"))
+            .collect::<String>()
+            .into();
+        tracing::info!(
+            "Remote control listening on ws://{}/?token={token}",
+            listener.local_addr()?
+        );
+
+        let (sender, requests) = mpsc::channel();
+        thread::spawn(move || {
+            for stream in listener.incoming() {
+                match stream {
+                    Ok(stream) => {
+                        let sender = sender.clone();
+                        let token = token.clone();
+                        thread::spawn(move || serve(stream, sender, &token));
+                    }
+                    Err(err) => tracing::warn!("Failed to accept remote connection: {err}"),
+                }
+            }
+        });
+        Ok(Self { requests })
+    }
+
+    /// Requests received since the last call. Call once per frame.
+    pub fn poll(&self) -> impl Iterator<Item = Request> + '_ {
+        self.requests.try_iter()
+    }
+}
+
+/// Forwards each message to the frame loop and sends back its answer.
+// The handshake callback's error type is set by tungstenite.
+#[allow(clippy::result_large_err)]
+fn serve(stream: TcpStream, requests: Sender<Request>, token: &str) {
+    let peer = stream.peer_addr().ok();
+    let check = |request: &tungstenite::handshake::server::Request, response: Response| {
+        if request.headers().contains_key("origin") {
+            return Err(refuse(
+                StatusCode::FORBIDDEN,
+                "browser connections are refused",
+            ));
+        }
+        if !presents_token(request, token) {
+            return Err(refuse(StatusCode::UNAUTHORIZED, "missing or wrong token"));
+        }
+        Ok(response)
+    };
+    let mut socket = match tungstenite::accept_hdr(stream, check) {
+        Ok(socket) => socket,
+        Err(err) => {
+            tracing::warn!("Remote handshake with {peer:?} failed: {err}");
+            return;
+        }
+    };
+    tracing::info!("Remote client {peer:?} connected");
+
+    loop {
+        let line = match socket.read() {
+            Ok(Message::Text(text)) => text.to_string(),
+            Ok(Message::Close(_)) | Err(_) => break,
+            Ok(_) => continue,
+        };
+        let (reply, response) = mpsc::channel();
+        if requests.send(Request { line, reply }).is_err() {
+            break;
+        }
+        // Dropped without an answer when the app shuts down.
+        let Ok(response) = response.recv() else {
+            break;
+        };
+        if socket.send(Message::text(response)).is_err() {
+            break;
+        }
+    }
+    tracing::info!("Remote client {peer:?} disconnected");
+}
+
+fn refuse(status: StatusCode, reason: &str) -> ErrorResponse {
+    let mut response = ErrorResponse::new(Some(reason.to_owned()));
+    *response.status_mut() = status;
+    response
+}
+
+/// Whether the handshake carries `token` in the query or an `Authorization` header.
+fn presents_token(request: &tungstenite::handshake::server::Request, token: &str) -> bool {
+    let from_query = request
+        .uri()
+        .query()
+        .into_iter()
+        .flat_map(|query| query.split('&'))
+        .filter_map(|pair| pair.strip_prefix("token="));
+    let from_header = request
+        .headers()
+        .get_all("authorization")
+        .into_iter()
+        .filter_map(|value| value.to_str().ok()?.strip_prefix("Bearer "));
+    from_query
+        .chain(from_header)
+        .any(|candidate| constant_time_eq(candidate.as_bytes(), token.as_bytes()))
+}
+
+/// Compares without returning early, so the time taken doesn't reveal the token.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tungstenite::handshake::server::Request as HandshakeRequest;
+
+    fn handshake(uri: &str, authorization: Option<&str>) -> HandshakeRequest {
+        let mut builder = HandshakeRequest::builder().uri(uri);
+        if let Some(value) = authorization {
+            builder = builder.header("Authorization", value);
+        }
+        builder.body(()).unwrap()
+    }
+
+    #[test]
+    fn token_is_accepted_from_the_query_or_header() {
+        assert!(presents_token(&handshake("/?token=abc", None), "abc"));
+        assert!(presents_token(&handshake("/?x=1&token=abc", None), "abc"));
+        assert!(presents_token(&handshake("/", Some("Bearer abc")), "abc"));
+    }
+
+    #[test]
+    fn missing_or_wrong_token_is_refused() {
+        assert!(!presents_token(&handshake("/", None), "abc"));
+        assert!(!presents_token(&handshake("/?token=abd", None), "abc"));
+        assert!(!presents_token(&handshake("/?token=ab", None), "abc"));
+        assert!(!presents_token(&handshake("/?token=", None), "abc"));
+        assert!(!presents_token(&handshake("/", Some("abc")), "abc"));
+        assert!(!presents_token(&handshake("/abc", None), "abc"));
+    }
+}
@@ -0,0 +1,54 @@
+use egui_wgpu::wgpu;
+
+pub const CLEAR_COLOR: wgpu::Color = wgpu::Color {
+    r: 0.1,
+    g: 0.2,
+    b: 0.3,
+    a: 1.0,
+};
+
+/// Converts a linear color channel to sRGB, for render targets that store
+/// colors as written instead of encoding them.
+pub fn linear_to_srgb(value: f64) -> f64 {
+    if value <= 0.0031308 {
+        value * 12.92
+    } else {
+        1.055 * value.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+/// Clears `view`, which has the given `format`, to the background color. Targets
+/// that don't encode sRGB themselves get the color encoded here, so the
+/// background looks the same in the window and in headless renders.
+pub fn draw(
+    encoder: &mut wgpu::CommandEncoder,
+    view: &wgpu::TextureView,
+    format: wgpu::TextureFormat,
+) {
+    let clear_color = if format.is_srgb() {
+        CLEAR_COLOR
+    } else {
+        wgpu::Color {
+            r: linear_to_srgb(CLEAR_COLOR.r),
+            g: linear_to_srgb(CLEAR_COLOR.g),
+            b: linear_to_srgb(CLEAR_COLOR.b),
+            a: CLEAR_COLOR.a,
+        }
+    };
+
+    // Clear the screen
+    let _rpass = encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+        label: Some("scene pass"),
+        color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+            view,
+            resolve_target: None,
+            ops: wgpu::Operations {
+                load: wgpu::LoadOp::Clear(clear_color),
+                store: wgpu::StoreOp::Store,
+            },
+        })],
+        depth_stencil_attachment: None,
+        timestamp_writes: None,
+        occlusion_query_set: None,
+    });
+}
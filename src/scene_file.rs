@@ -0,0 +1,153 @@
+use crate::entities::{Renderable, Transform, Velocity};
+use crate::navigation::Waypoint;
+use glam::Vec3;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+
+/// Where the main menu looks for scene files.
+pub const SCENE_DIR: &str = "scenes";
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct CameraPose {
+    pub position: Vec3,
+    pub target: Vec3,
+}
+
+impl Default for CameraPose {
+    fn default() -> Self {
+        Self {
+            position: Vec3::new(0.0, 5.0, 0.0),
+            target: Vec3::new(0.0, 5.0, -1.0),
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ParticleSettings {
+    pub enabled: bool,
+    pub emitter: Vec3,
+    pub gravity: f32,
+    pub size: f32,
+}
+
+impl Default for ParticleSettings {
+    fn default() -> Self {
+        Self {
+            enabled: true,
+            emitter: Vec3::new(0.0, 2.0, -15.0),
+            gravity: 9.81,
+            size: 0.05,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Serialize, Deserialize)]
+pub struct EntityDescription {
+    pub transform: Transform,
+    pub velocity: Option<Velocity>,
+    pub renderable: Option<Renderable>,
+}
+
+/// A complete demo scene that can be shared as a RON file: where the camera
+/// is, how the particles look, which entities exist and the saved waypoints.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct SceneFile {
+    pub camera: CameraPose,
+    pub particles: ParticleSettings,
+    pub entities: Vec<EntityDescription>,
+    pub waypoints: Vec<Waypoint>,
+}
+
+impl SceneFile {
+    pub fn load(path: impl AsRef<Path>) -> io::Result<Self> {
+        let contents = fs::read_to_string(path)?;
+        ron::from_str(&contents).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))
+    }
+
+    pub fn save(&self, path: impl AsRef<Path>) -> io::Result<()> {
+        let contents = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+            .map_err(io::Error::other)?;
+        fs::write(path, contents)
+    }
+
+    /// The `.ron` files in [`SCENE_DIR`], sorted by name. Empty if the directory doesn't exist.
+    pub fn list() -> Vec<PathBuf> {
+        let Ok(entries) = fs::read_dir(SCENE_DIR) else {
+            return Vec::new();
+        };
+        let mut paths: Vec<PathBuf> = entries
+            .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+            .filter(|path| path.extension().is_some_and(|ext| ext == "ron"))
+            .collect();
+        paths.sort();
+        paths
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scene_round_trips_through_a_file() {
+        let scene = SceneFile {
+            camera: CameraPose {
+                position: Vec3::new(1.0, 2.0, 3.0),
+                target: Vec3::new(-4.5, 0.25, 8.0),
+            },
+            particles: ParticleSettings {
+                enabled: false,
+                emitter: Vec3::new(5.0, -1.0, 2.5),
+                gravity: 1.62,
+                size: 0.2,
+            },
+            entities: vec![
+                EntityDescription {
+                    transform: Transform::from_position(Vec3::new(0.0, 1.0, -10.0)),
+                    velocity: Some(Velocity(Vec3::new(0.0, 0.0, -0.5))),
+                    renderable: Some(Renderable {
+                        color: [1.0, 0.3, 0.2, 1.0],
+                    }),
+                },
+                EntityDescription {
+                    transform: Transform::from_position(Vec3::ZERO),
+                    velocity: None,
+                    renderable: None,
+                },
+            ],
+            waypoints: vec![Waypoint {
+                name: "spawn point".to_owned(),
+                position: Vec3::new(0.0, 5.0, 0.0),
+            }],
+        };
+
+        let path = std::env::temp_dir().join(format!("scene-{}.ron", std::process::id()));
+        scene.save(&path).unwrap();
+        let loaded = SceneFile::load(&path);
+        fs::remove_file(&path).unwrap();
+
+        assert_eq!(loaded.unwrap(), scene);
+    }
+
+    #[test]
+    fn missing_fields_use_defaults() {
+        let scene: SceneFile = ron::from_str("(particles: (gravity: 3.0))").unwrap();
+        assert_eq!(scene.particles.gravity, 3.0);
+        assert_eq!(scene.particles.size, ParticleSettings::default().size);
+        assert_eq!(scene.camera, CameraPose::default());
+        assert!(scene.entities.is_empty());
+    }
+
+    #[test]
+    fn demo_scene_loads() {
+        let path = Path::new(env!("CARGO_MANIFEST_DIR"))
+            .join(SCENE_DIR)
+            .join("demo.ron");
+        let scene = SceneFile::load(path).unwrap();
+        assert!(!scene.entities.is_empty());
+    }
+}
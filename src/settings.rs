@@ -0,0 +1,323 @@
+use crate::camera::CameraSettings;
+use crate::hotkeys::Hotkeys;
+use egui_wgpu::wgpu;
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::io;
+use std::path::PathBuf;
+
+/// How frames are queued for presentation.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum PresentMode {
+    /// Vsync, frames wait in a queue. Always supported.
+    Fifo,
+    /// Vsync, but newer frames replace queued ones for lower latency.
+    Mailbox,
+    /// No vsync, may tear.
+    Immediate,
+}
+
+impl PresentMode {
+    pub const ALL: [PresentMode; 3] = [
+        PresentMode::Fifo,
+        PresentMode::Mailbox,
+        PresentMode::Immediate,
+    ];
+
+    pub fn to_wgpu(self) -> wgpu::PresentMode {
+        match self {
+            PresentMode::Fifo => wgpu::PresentMode::Fifo,
+            PresentMode::Mailbox => wgpu::PresentMode::Mailbox,
+            PresentMode::Immediate => wgpu::PresentMode::Immediate,
+        }
+    }
+}
+
+/// Bundles of the quality settings. The render scale is the only quality
+/// setting the template has; games built on it can bundle their own as well.
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum QualityPreset {
+    Low,
+    Medium,
+    High,
+    Ultra,
+    /// The settings were changed by hand after picking a preset.
+    Custom,
+}
+
+impl QualityPreset {
+    /// The presets that can be picked.
+    pub const ALL: [QualityPreset; 4] = [
+        QualityPreset::Low,
+        QualityPreset::Medium,
+        QualityPreset::High,
+        QualityPreset::Ultra,
+    ];
+
+    /// A starting preset for the kind of adapter: less for integrated and
+    /// software renderers.
+    pub fn detect(info: &wgpu::AdapterInfo) -> Self {
+        match info.device_type {
+            wgpu::DeviceType::DiscreteGpu => QualityPreset::High,
+            wgpu::DeviceType::Cpu => QualityPreset::Low,
+            wgpu::DeviceType::IntegratedGpu
+            | wgpu::DeviceType::VirtualGpu
+            | wgpu::DeviceType::Other => QualityPreset::Medium,
+        }
+    }
+
+    /// `None` for [`QualityPreset::Custom`], which keeps the current scale.
+    pub fn render_scale(self) -> Option<f32> {
+        match self {
+            QualityPreset::Low => Some(0.5),
+            QualityPreset::Medium => Some(0.75),
+            QualityPreset::High => Some(1.0),
+            QualityPreset::Ultra => Some(1.5),
+            QualityPreset::Custom => None,
+        }
+    }
+}
+
+/// User preferences for how the mouse drives the camera.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct MouseSettings {
+    /// Use unaccelerated device motion instead of the OS cursor movement.
+    pub raw_motion: bool,
+    pub invert_y: bool,
+    /// Lock the cursor when the viewport is clicked. When disabled the camera
+    /// only looks around while the right mouse button is held.
+    pub lock_on_click: bool,
+}
+
+impl Default for MouseSettings {
+    fn default() -> Self {
+        Self {
+            raw_motion: true,
+            invert_y: false,
+            lock_on_click: true,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq, Serialize, Deserialize)]
+pub enum ThemePreset {
+    Dark,
+    Light,
+}
+
+/// Look of the egui interface.
+#[derive(Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct ThemeSettings {
+    pub preset: ThemePreset,
+    /// Color of selections and links, as sRGB.
+    pub accent: [u8; 3],
+    /// Size of body text in points; other text styles scale along.
+    pub font_size: f32,
+}
+
+impl Default for ThemeSettings {
+    fn default() -> Self {
+        Self {
+            preset: ThemePreset::Dark,
+            accent: [0, 92, 128],
+            font_size: 12.5,
+        }
+    }
+}
+
+impl ThemeSettings {
+    pub fn apply(&self, ctx: &egui::Context) {
+        ctx.set_theme(match self.preset {
+            ThemePreset::Dark => egui::Theme::Dark,
+            ThemePreset::Light => egui::Theme::Light,
+        });
+        let [r, g, b] = self.accent;
+        let accent = egui::Color32::from_rgb(r, g, b);
+        ctx.style_mut(|style| {
+            style.visuals.selection.bg_fill = accent;
+            style.visuals.hyperlink_color = accent;
+            for (text_style, font) in style.text_styles.iter_mut() {
+                font.size = match text_style {
+                    egui::TextStyle::Heading => self.font_size * 1.44,
+                    egui::TextStyle::Small => self.font_size * 0.72,
+                    _ => self.font_size,
+                };
+            }
+        });
+    }
+}
+
+/// Everything persisted between runs, stored as TOML in the platform config directory.
+#[derive(Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Settings {
+    pub window_width: u32,
+    pub window_height: u32,
+    /// Name of the graphics adapter to prefer, if it is still present.
+    pub adapter: Option<String>,
+    pub present_mode: PresentMode,
+    /// Only redraw when something changed instead of continuously.
+    pub low_power: bool,
+    /// Frame rate cap, or 0 for none.
+    pub fps_limit: u32,
+    /// Resolution of the 3D scene relative to the window. The UI stays sharp.
+    pub render_scale: f32,
+    /// The preset picked last, or `None` to detect one from the adapter on the
+    /// next start.
+    pub quality: Option<QualityPreset>,
+    pub ui_scale: f32,
+    pub theme: ThemeSettings,
+    pub camera: CameraSettings,
+    pub mouse: MouseSettings,
+    pub keybinds: Hotkeys,
+}
+
+impl Default for Settings {
+    fn default() -> Self {
+        Self {
+            window_width: 1360,
+            window_height: 768,
+            adapter: None,
+            present_mode: PresentMode::Fifo,
+            low_power: false,
+            fps_limit: 0,
+            render_scale: 1.0,
+            quality: None,
+            ui_scale: 1.0,
+            theme: ThemeSettings::default(),
+            camera: CameraSettings::default(),
+            mouse: MouseSettings::default(),
+            keybinds: Hotkeys::default(),
+        }
+    }
+}
+
+impl Settings {
+    pub fn path() -> Option<PathBuf> {
+        dirs::config_dir().map(|dir| dir.join("wgpu-egui-template").join("settings.toml"))
+    }
+
+    /// Loads the settings file, falling back to defaults if it is missing or invalid.
+    pub fn load() -> Self {
+        let Some(path) = Self::path() else {
+            return Self::default();
+        };
+        match fs::read_to_string(&path) {
+            Ok(contents) => match toml::from_str::<Self>(&contents) {
+                Ok(settings) => settings.clamped(),
+                Err(err) => {
+                    tracing::warn!("Ignoring invalid settings in {}: {err}", path.display());
+                    Self::default()
+                }
+            },
+            Err(_) => Self::default(),
+        }
+    }
+
+    /// Switches to `preset` and sets the settings it bundles.
+    pub fn apply_quality(&mut self, preset: QualityPreset) {
+        self.quality = Some(preset);
+        if let Some(render_scale) = preset.render_scale() {
+            self.render_scale = render_scale;
+        }
+    }
+
+    /// Brings hand-edited values into the ranges the UI allows, so a zero
+    /// window size or scale can't reach the renderer.
+    pub fn clamped(mut self) -> Self {
+        let defaults = Self::default();
+        self.window_width = self.window_width.max(1);
+        self.window_height = self.window_height.max(1);
+        self.ui_scale = clamp_or(self.ui_scale, 0.3..=3.0, defaults.ui_scale);
+        self.render_scale = clamp_or(self.render_scale, 0.25..=2.0, defaults.render_scale);
+        self
+    }
+
+    pub fn save(&self) -> io::Result<()> {
+        let path = Self::path()
+            .ok_or_else(|| io::Error::new(io::ErrorKind::NotFound, "no config directory"))?;
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)?;
+        }
+        let contents = toml::to_string_pretty(self).map_err(io::Error::other)?;
+        fs::write(path, contents)
+    }
+}
+
+/// Clamps `value` into `range`, or returns `default` if it isn't a number.
+fn clamp_or(value: f32, range: std::ops::RangeInclusive<f32>, default: f32) -> f32 {
+    if value.is_nan() {
+        default
+    } else {
+        value.clamp(*range.start(), *range.end())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hotkeys::{Action, Chord, InputContext};
+    use winit::keyboard::{KeyCode, ModifiersState};
+
+    #[test]
+    fn settings_round_trip_through_toml() {
+        let mut settings = Settings {
+            window_width: 1920,
+            window_height: 1080,
+            adapter: Some("Test GPU".to_owned()),
+            present_mode: PresentMode::Mailbox,
+            low_power: true,
+            fps_limit: 144,
+            render_scale: 0.75,
+            quality: Some(QualityPreset::Custom),
+            ui_scale: 1.5,
+            ..Settings::default()
+        };
+        settings.keybinds.bind(
+            Chord::new(KeyCode::KeyS).with(ModifiersState::CONTROL | ModifiersState::SHIFT),
+            None,
+            Action::SavePhoto,
+        );
+        settings.keybinds.bind(
+            Chord::new(KeyCode::KeyP).with(ModifiersState::ALT),
+            Some(InputContext::Menu),
+            Action::TogglePhotoMode,
+        );
+
+        let contents = toml::to_string_pretty(&settings).unwrap();
+        let loaded: Settings = toml::from_str(&contents).unwrap();
+        assert!(loaded == settings, "settings changed in:\n{contents}");
+    }
+
+    #[test]
+    fn presets_set_the_render_scale_and_custom_keeps_it() {
+        let mut settings = Settings::default();
+        settings.apply_quality(QualityPreset::Low);
+        assert_eq!(settings.render_scale, 0.5);
+        assert_eq!(settings.quality, Some(QualityPreset::Low));
+
+        settings.render_scale = 0.6;
+        settings.apply_quality(QualityPreset::Custom);
+        assert_eq!(settings.render_scale, 0.6);
+    }
+
+    #[test]
+    fn out_of_range_values_are_clamped() {
+        let settings: Settings = toml::from_str(
+            "window_width = 0\nwindow_height = 0\nui_scale = 0.0\nrender_scale = 10.0",
+        )
+        .unwrap();
+        let settings = settings.clamped();
+        assert_eq!((settings.window_width, settings.window_height), (1, 1));
+        assert_eq!(settings.ui_scale, 0.3);
+        assert_eq!(settings.render_scale, 2.0);
+
+        let settings = Settings {
+            ui_scale: f32::NAN,
+            ..Settings::default()
+        };
+        assert_eq!(settings.clamped().ui_scale, 1.0);
+    }
+}
@@ -0,0 +1,151 @@
+use crate::app::select_surface_format;
+use crate::egui_tools::EguiRenderer;
+use crate::settings::ThemeSettings;
+use egui_wgpu::{wgpu, ScreenDescriptor};
+use std::sync::Arc;
+use winit::event::WindowEvent;
+use winit::window::{Window, WindowId};
+
+/// A secondary OS window with its own surface and egui context that shares the
+/// main window's device, e.g. to move the profiler to another monitor.
+pub struct ToolWindow {
+    window: Arc<Window>,
+    surface: wgpu::Surface<'static>,
+    config: wgpu::SurfaceConfiguration,
+    egui_renderer: EguiRenderer,
+    /// Theme last applied to egui, to reapply it only after a change.
+    applied_theme: Option<ThemeSettings>,
+}
+
+impl ToolWindow {
+    /// Returns `None` if `adapter` can't present to the window.
+    pub fn new(
+        instance: &wgpu::Instance,
+        adapter: &wgpu::Adapter,
+        device: &wgpu::Device,
+        window: Arc<Window>,
+    ) -> Option<Self> {
+        let surface = instance.create_surface(window.clone()).ok()?;
+        if !adapter.is_surface_supported(&surface) {
+            return None;
+        }
+        let capabilities = surface.get_capabilities(adapter);
+        let size = window.inner_size();
+        let config = wgpu::SurfaceConfiguration {
+            usage: wgpu::TextureUsages::RENDER_ATTACHMENT,
+            format: select_surface_format(&capabilities.formats),
+            width: size.width.max(1),
+            height: size.height.max(1),
+            // Drawn in the main window's frame, which already waits for vsync.
+            present_mode: wgpu::PresentMode::AutoNoVsync,
+            desired_maximum_frame_latency: 2,
+            alpha_mode: capabilities.alpha_modes[0],
+            view_formats: vec![],
+        };
+        surface.configure(device, &config);
+        let egui_renderer = EguiRenderer::new(device, config.format, None, 1, &window);
+
+        Some(Self {
+            window,
+            surface,
+            config,
+            egui_renderer,
+            applied_theme: None,
+        })
+    }
+
+    pub fn id(&self) -> WindowId {
+        self.window.id()
+    }
+
+    pub fn context(&self) -> &egui::Context {
+        self.egui_renderer.context()
+    }
+
+    /// Applies `theme` to the window's egui context unless it already has it.
+    pub fn set_theme(&mut self, theme: ThemeSettings) {
+        if self.applied_theme != Some(theme) {
+            theme.apply(self.egui_renderer.context());
+            self.applied_theme = Some(theme);
+        }
+    }
+
+    /// Asks for a `RedrawRequested` event, to be answered with [`ToolWindow::render`].
+    pub fn request_redraw(&self) {
+        self.window.request_redraw();
+    }
+
+    /// Returns whether egui needs a repaint in response to the event.
+    pub fn handle_event(&mut self, device: &wgpu::Device, event: &WindowEvent) -> bool {
+        if let WindowEvent::Resized(size) = event {
+            if size.width > 0 && size.height > 0 {
+                self.config.width = size.width;
+                self.config.height = size.height;
+                self.surface.configure(device, &self.config);
+            }
+        }
+        self.egui_renderer.handle_input(&self.window, event)
+    }
+
+    /// Draws one frame of the egui UI built by `ui`, and asks for the next one
+    /// right away while egui is animating. Frames the surface can't provide are
+    /// skipped.
+    pub fn render(
+        &mut self,
+        device: &wgpu::Device,
+        queue: &wgpu::Queue,
+        ui: impl FnOnce(&egui::Context),
+    ) {
+        let surface_texture = match self.surface.get_current_texture() {
+            Ok(texture) => texture,
+            Err(wgpu::SurfaceError::Lost | wgpu::SurfaceError::Outdated) => {
+                self.surface.configure(device, &self.config);
+                return;
+            }
+            Err(err) => {
+                tracing::warn!("Dropped tool window frame: {err}");
+                return;
+            }
+        };
+        let view = surface_texture
+            .texture
+            .create_view(&wgpu::TextureViewDescriptor::default());
+        let mut encoder = device.create_command_encoder(&wgpu::CommandEncoderDescriptor {
+            label: Some("tool window encoder"),
+        });
+        encoder.begin_render_pass(&wgpu::RenderPassDescriptor {
+            label: Some("tool window clear"),
+            color_attachments: &[Some(wgpu::RenderPassColorAttachment {
+                view: &view,
+                resolve_target: None,
+                ops: wgpu::Operations {
+                    load: wgpu::LoadOp::Clear(wgpu::Color::BLACK),
+                    store: wgpu::StoreOp::Store,
+                },
+            })],
+            depth_stencil_attachment: None,
+            timestamp_writes: None,
+            occlusion_query_set: None,
+        });
+
+        self.egui_renderer.begin_frame(&self.window);
+        ui(self.egui_renderer.context());
+        let screen_descriptor = ScreenDescriptor {
+            size_in_pixels: [self.config.width, self.config.height],
+            pixels_per_point: self.window.scale_factor() as f32,
+        };
+        let repaint_delay = self.egui_renderer.end_frame_and_draw(
+            device,
+            queue,
+            &mut encoder,
+            &self.window,
+            &view,
+            screen_descriptor,
+        );
+        queue.submit(Some(encoder.finish()));
+        surface_texture.present();
+        if repaint_delay.is_zero() {
+            self.window.request_redraw();
+        }
+    }
+}
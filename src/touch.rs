@@ -0,0 +1,143 @@
+use glam::Vec2;
+use std::time::Duration;
+use web_time::Instant;
+use winit::event::{Touch, TouchPhase};
+
+const JOYSTICK_RADIUS: f32 = 80.0;
+const LOOK_SENSITIVITY: f32 = 0.25;
+const TAP_MAX_DURATION: Duration = Duration::from_millis(250);
+const TAP_MAX_DISTANCE: f32 = 12.0;
+
+struct Joystick {
+    id: u64,
+    origin: Vec2,
+    current: Vec2,
+}
+
+struct Drag {
+    id: u64,
+    start: Vec2,
+    last: Vec2,
+    started_at: Instant,
+}
+
+/// Maps raw touch events to camera controls: the left half of the screen is a
+/// virtual joystick for movement, the right half drags to look around and a
+/// short tap anywhere on it is reported as an interaction.
+pub struct TouchControls {
+    joystick: Option<Joystick>,
+    drag: Option<Drag>,
+    look_delta: Vec2,
+    tap: Option<Vec2>,
+}
+
+impl Default for TouchControls {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TouchControls {
+    pub fn new() -> Self {
+        Self {
+            joystick: None,
+            drag: None,
+            look_delta: Vec2::ZERO,
+            tap: None,
+        }
+    }
+
+    pub fn handle_touch(&mut self, touch: &Touch, screen_width: f32) {
+        let position = Vec2::new(touch.location.x as f32, touch.location.y as f32);
+
+        match touch.phase {
+            TouchPhase::Started => {
+                if position.x < screen_width * 0.5 {
+                    if self.joystick.is_none() {
+                        self.joystick = Some(Joystick {
+                            id: touch.id,
+                            origin: position,
+                            current: position,
+                        });
+                    }
+                } else if self.drag.is_none() {
+                    self.drag = Some(Drag {
+                        id: touch.id,
+                        start: position,
+                        last: position,
+                        started_at: Instant::now(),
+                    });
+                }
+            }
+            TouchPhase::Moved => {
+                if let Some(joystick) = self.joystick.as_mut().filter(|j| j.id == touch.id) {
+                    joystick.current = position;
+                } else if let Some(drag) = self.drag.as_mut().filter(|d| d.id == touch.id) {
+                    self.look_delta += (position - drag.last) * LOOK_SENSITIVITY;
+                    drag.last = position;
+                }
+            }
+            TouchPhase::Ended | TouchPhase::Cancelled => {
+                if self.joystick.as_ref().is_some_and(|j| j.id == touch.id) {
+                    self.joystick = None;
+                } else if let Some(drag) = self.drag.take_if(|d| d.id == touch.id) {
+                    let is_tap = touch.phase == TouchPhase::Ended
+                        && drag.started_at.elapsed() <= TAP_MAX_DURATION
+                        && drag.start.distance(position) <= TAP_MAX_DISTANCE;
+                    if is_tap {
+                        self.tap = Some(position);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Whether a finger is currently driving the joystick or the camera.
+    pub fn is_active(&self) -> bool {
+        self.joystick.is_some() || self.drag.is_some()
+    }
+
+    /// Joystick deflection in `[-1, 1]`, `x` to the right and `y` forward.
+    pub fn movement(&self) -> Vec2 {
+        match &self.joystick {
+            Some(joystick) => {
+                let offset = (joystick.current - joystick.origin).clamp_length_max(JOYSTICK_RADIUS)
+                    / JOYSTICK_RADIUS;
+                Vec2::new(offset.x, -offset.y)
+            }
+            None => Vec2::ZERO,
+        }
+    }
+
+    pub fn take_look_delta(&mut self) -> Vec2 {
+        std::mem::take(&mut self.look_delta)
+    }
+
+    pub fn take_tap(&mut self) -> Option<Vec2> {
+        self.tap.take()
+    }
+
+    /// Draws the virtual joystick while it is held. Positions are in physical pixels.
+    pub fn draw(&self, ctx: &egui::Context) {
+        let Some(joystick) = &self.joystick else {
+            return;
+        };
+
+        let ppp = ctx.pixels_per_point();
+        let to_point = |v: Vec2| egui::pos2(v.x / ppp, v.y / ppp);
+        let knob = joystick.origin
+            + (joystick.current - joystick.origin).clamp_length_max(JOYSTICK_RADIUS);
+
+        let painter = ctx.layer_painter(egui::LayerId::background());
+        painter.circle_stroke(
+            to_point(joystick.origin),
+            JOYSTICK_RADIUS / ppp,
+            egui::Stroke::new(2.0, egui::Color32::from_white_alpha(96)),
+        );
+        painter.circle_filled(
+            to_point(knob),
+            JOYSTICK_RADIUS * 0.4 / ppp,
+            egui::Color32::from_white_alpha(64),
+        );
+    }
+}
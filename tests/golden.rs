@@ -0,0 +1,143 @@
+//! Renders fixed scenes offscreen and compares them against reference images in
+//! `tests/golden`. Run with `UPDATE_GOLDEN=1` to (re)write the references after
+//! an intended change. Failed renders are saved to `target/golden-failures`.
+
+use egui_rust_wgpu_hello::entities::{Renderable, Transform};
+use egui_rust_wgpu_hello::headless::{HeadlessError, HeadlessRenderer, RgbaImage};
+use egui_rust_wgpu_hello::scene_file::{
+    CameraPose, EntityDescription, ParticleSettings, SceneFile,
+};
+use glam::Vec3;
+use std::path::PathBuf;
+
+/// Channel difference a pixel may have before it counts as changed. Absorbs
+/// rounding and dithering differences between GPUs and drivers.
+const MAX_CHANNEL_DELTA: u8 = 8;
+/// Fraction of pixels that may change before the test fails.
+const MAX_CHANGED_FRACTION: f64 = 0.001;
+
+fn renderer() -> Option<HeadlessRenderer> {
+    match pollster::block_on(HeadlessRenderer::new()) {
+        Ok(renderer) => Some(renderer),
+        Err(HeadlessError::NoAdapter) => {
+            eprintln!("Skipping golden image test: no graphics adapter available");
+            None
+        }
+        Err(err) => panic!("Failed to set up the headless renderer: {err}"),
+    }
+}
+
+fn assert_matches_golden(name: &str, image: &RgbaImage) {
+    let root = PathBuf::from(env!("CARGO_MANIFEST_DIR"));
+    let reference_path = root.join("tests/golden").join(format!("{name}.png"));
+
+    if std::env::var_os("UPDATE_GOLDEN").is_some() {
+        image
+            .save_png(&reference_path)
+            .expect("Failed to write reference image");
+        return;
+    }
+
+    let reference = RgbaImage::load_png(&reference_path).unwrap_or_else(|err| {
+        panic!(
+            "Failed to load {}: {err}. Run with UPDATE_GOLDEN=1 to create it.",
+            reference_path.display()
+        )
+    });
+    assert_eq!(
+        (image.width, image.height),
+        (reference.width, reference.height),
+        "{name}: size differs from the reference"
+    );
+
+    let changed = image
+        .pixels
+        .chunks_exact(4)
+        .zip(reference.pixels.chunks_exact(4))
+        .filter(|(a, b)| {
+            a.iter()
+                .zip(*b)
+                .any(|(a, b)| a.abs_diff(*b) > MAX_CHANNEL_DELTA)
+        })
+        .count();
+    let changed_fraction = changed as f64 / (image.width * image.height) as f64;
+    if changed_fraction > MAX_CHANGED_FRACTION {
+        let failure_dir = root.join("target/golden-failures");
+        std::fs::create_dir_all(&failure_dir).expect("Failed to create failure directory");
+        let failure_path = failure_dir.join(format!("{name}.png"));
+        image
+            .save_png(&failure_path)
+            .expect("Failed to write failed render");
+        panic!(
+            "{name}: {changed} pixels ({:.3}%) differ from the reference, render saved to {}",
+            changed_fraction * 100.0,
+            failure_path.display()
+        );
+    }
+}
+
+#[test]
+fn clear_scene() {
+    let Some(renderer) = renderer() else {
+        return;
+    };
+    let image = renderer
+        .render(320, 180)
+        .expect("Failed to read back the render");
+    assert_matches_golden("clear_scene", &image);
+}
+
+#[test]
+fn particle_fountain() {
+    let Some(renderer) = renderer() else {
+        return;
+    };
+    let scene = SceneFile {
+        camera: CameraPose {
+            position: Vec3::new(0.0, 4.0, -5.0),
+            target: Vec3::new(0.0, 4.0, -15.0),
+        },
+        particles: ParticleSettings {
+            size: 0.15,
+            ..ParticleSettings::default()
+        },
+        ..SceneFile::default()
+    };
+    // Two seconds in, so the first particles are falling and some have respawned.
+    let image = renderer
+        .render_scene(&scene, 120, 320, 180)
+        .expect("Failed to read back the render");
+    assert_matches_golden("particle_fountain", &image);
+}
+
+#[test]
+fn entity_sprites() {
+    let Some(renderer) = renderer() else {
+        return;
+    };
+    let entity = |x: f32, color| EntityDescription {
+        transform: Transform::from_position(Vec3::new(x, 4.0, -10.0)),
+        velocity: None,
+        renderable: Some(Renderable { color }),
+    };
+    let scene = SceneFile {
+        camera: CameraPose {
+            position: Vec3::new(0.0, 4.0, 0.0),
+            target: Vec3::new(0.0, 4.0, -10.0),
+        },
+        particles: ParticleSettings {
+            enabled: false,
+            ..ParticleSettings::default()
+        },
+        entities: vec![
+            entity(-2.0, [1.0, 0.3, 0.2, 1.0]),
+            entity(0.0, [0.2, 0.8, 0.3, 1.0]),
+            entity(2.0, [0.2, 0.4, 1.0, 0.5]),
+        ],
+        ..SceneFile::default()
+    };
+    let image = renderer
+        .render_scene(&scene, 0, 320, 180)
+        .expect("Failed to read back the render");
+    assert_matches_golden("entity_sprites", &image);
+}